@@ -1,9 +1,39 @@
 use {
     anyhow::*,
     directories_next::ProjectDirs,
+    std::path::PathBuf,
 };
 
+/// the env var which, when set, gives the directory holding
+/// both the configuration and the data of starry
+pub static STARRY_HOME_ENV_VAR: &str = "STARRY_HOME";
+
 /// return the instance of ProjectDirs holding the app specific paths
 pub fn app_dirs() -> Result<ProjectDirs> {
     ProjectDirs::from("org", "dystroy", "starry").context("Unable to find app directories")
 }
+
+/// return the directory set with the STARRY_HOME env var, if any
+pub fn starry_home() -> Option<PathBuf> {
+    std::env::var_os(STARRY_HOME_ENV_VAR)
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// return the directory of the configuration: STARRY_HOME
+/// when it's set, the standard config dir otherwise
+pub fn config_dir() -> Result<PathBuf> {
+    match starry_home() {
+        Some(home) => Ok(home),
+        None => Ok(app_dirs()?.config_dir().to_path_buf()),
+    }
+}
+
+/// return the directory of the data: STARRY_HOME
+/// when it's set, the standard data dir otherwise
+pub fn data_dir() -> Result<PathBuf> {
+    match starry_home() {
+        Some(home) => Ok(home),
+        None => Ok(app_dirs()?.data_dir().to_path_buf()),
+    }
+}
@@ -1,4 +1,8 @@
-use argh::FromArgs;
+use {
+    crate::*,
+    argh::FromArgs,
+    std::path::PathBuf,
+};
 
 #[derive(Debug, FromArgs)]
 /// The history of current stars tells only half the starry.
@@ -27,6 +31,14 @@ pub struct Args {
     #[argh(option, default = "Default::default()")]
     /// color and style: 'yes', 'no' or 'auto' (auto should be good in most cases)
     pub color: BoolArg,
+
+    /// the directory of the stored observations (default: $STARRY_HOME or the standard data dir)
+    #[argh(option)]
+    pub data_dir: Option<PathBuf>,
+
+    /// the configuration file (default: config.json in $STARRY_HOME or the standard config dir)
+    #[argh(option)]
+    pub config: Option<PathBuf>,
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -38,8 +50,17 @@ pub enum ArgsCommand {
     Unfollow(UnfollowCommand),
     Gaze(GazeCommand),
     Extract(ExtractCommand),
+    Graph(GraphCommand),
+    Gaps(GapsCommand),
     Check(CheckCommand),
     List(ListCommand),
+    Migrate(MigrateCommand),
+    Watch(WatchCommand),
+    Report(ReportCommand),
+    Backfill(BackfillCommand),
+    Track(TrackCommand),
+    Untrack(UntrackCommand),
+    Who(WhoCommand),
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -61,7 +82,7 @@ pub struct GetCommand {
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
-/// check the existence of a user
+/// check the existence of a user, organization or repository (owner/name)
 #[argh(subcommand, name = "check")]
 pub struct CheckCommand {
     #[argh(positional)]
@@ -69,7 +90,7 @@ pub struct CheckCommand {
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
-/// start following a github user
+/// start following a github user, organization or repository (owner/name)
 #[argh(subcommand, name = "follow")]
 pub struct FollowCommand {
     #[argh(positional)]
@@ -77,7 +98,7 @@ pub struct FollowCommand {
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
-/// stop following a github user
+/// stop following a github user, organization or repository (owner/name)
 #[argh(subcommand, name = "unfollow")]
 pub struct UnfollowCommand {
     #[argh(positional)]
@@ -87,14 +108,62 @@ pub struct UnfollowCommand {
 #[derive(FromArgs, PartialEq, Debug)]
 /// look at the stars (default command)
 #[argh(subcommand, name = "gaze")]
-pub struct GazeCommand {}
+pub struct GazeCommand {
+    /// output format instead of the report: 'json', 'csv', 'ndjson' or 'markdown'
+    #[argh(option)]
+    pub format: Option<OutputFormat>,
+    /// the only list to write with --format: 'changes', 'follows' or 'starred' (default: changes, or all of them in json)
+    #[argh(option)]
+    pub feed: Option<Feed>,
+    /// don't query the forges, show the last stored changes
+    #[argh(switch)]
+    pub offline: bool,
+}
 
 #[derive(FromArgs, PartialEq, Debug)]
-/// extract time series for one or several user or repo
+/// extract time series for one or several user or repo (owner/name), or of the followers or followed users of a user (login:followers, login:following)
 #[argh(subcommand, name = "extract")]
 pub struct ExtractCommand {
     #[argh(positional)]
     pub names: Vec<String>,
+    /// the metric: stars, forks, watchers, issues, prs or downloads (default: stars)
+    #[argh(option, default = "Metric::Stars")]
+    pub metric: Metric,
+    /// add a line at every check, even when stars didn't change
+    #[argh(switch)]
+    pub heartbeats: bool,
+    /// output format: 'csv', 'json', 'ndjson' or 'markdown' (default: csv)
+    #[argh(option, default = "OutputFormat::Csv")]
+    pub format: OutputFormat,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// draw the time series of one or several user or repo in the terminal (same names as extract)
+#[argh(subcommand, name = "graph")]
+pub struct GraphCommand {
+    #[argh(positional)]
+    pub names: Vec<String>,
+    /// the metric: stars, forks, watchers, issues, prs or downloads (default: stars)
+    #[argh(option, default = "Metric::Stars")]
+    pub metric: Metric,
+    /// add a point at every check, even when stars didn't change
+    #[argh(switch)]
+    pub heartbeats: bool,
+    /// number of lines of the plot (default: 20)
+    #[argh(option, default = "20")]
+    pub height: usize,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// list the periods during which users weren't checked
+#[argh(subcommand, name = "gaps")]
+pub struct GapsCommand {
+    /// logins of the users (default: all users in database)
+    #[argh(positional)]
+    pub logins: Vec<String>,
+    /// minimal duration of a reported gap, eg `12h` or `3d` (default: 1d)
+    #[argh(option, default = "DurationArg(chrono::Duration::days(1))")]
+    pub min: DurationArg,
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -103,6 +172,95 @@ pub struct ExtractCommand {
 pub struct ListCommand {
     #[argh(positional)]
     pub login: Option<String>,
+    /// the metric: stars, forks, watchers, issues, prs or downloads (default: stars)
+    #[argh(option, default = "Metric::Stars")]
+    pub metric: Metric,
+    /// output format: 'csv', 'json', 'ndjson' or 'markdown' (default: csv)
+    #[argh(option, default = "OutputFormat::Csv")]
+    pub format: OutputFormat,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// copy all observations to another storage, eg `starry migrate --to sqlite`
+#[argh(subcommand, name = "migrate")]
+pub struct MigrateCommand {
+    /// the current storage: 'csv', 'csv-delta' or 'sqlite' (default: csv)
+    #[argh(option, default = "StorageKind::Csv")]
+    pub from: StorageKind,
+    /// the new storage: 'csv', 'csv-delta' or 'sqlite'
+    #[argh(option)]
+    pub to: StorageKind,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// keep running, fetching the stars of every followed owner once per interval
+#[argh(subcommand, name = "watch")]
+pub struct WatchCommand {
+    /// time between two fetches of an owner, eg `30m` or `6h` (default: 1h)
+    #[argh(option, default = "DurationArg(chrono::Duration::hours(1))")]
+    pub interval: DurationArg,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// report the changes over a period, from the stored observations
+#[argh(subcommand, name = "report")]
+pub struct ReportCommand {
+    /// start of the period, as a duration before now, eg `7d` (default: 7d)
+    #[argh(option)]
+    pub since: Option<DurationArg>,
+    /// start of the period, eg `2024-03-01`
+    #[argh(option)]
+    pub from: Option<DateArg>,
+    /// end of the period, eg `2024-03-31` (default: now)
+    #[argh(option)]
+    pub to: Option<DateArg>,
+    /// output format instead of the report: 'json', 'csv', 'ndjson' or 'markdown'
+    #[argh(option)]
+    pub format: Option<OutputFormat>,
+    /// the only list to write with --format: 'changes', 'follows' or 'starred' (default: changes, or all of them in json)
+    #[argh(option)]
+    pub feed: Option<Feed>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// reconstruct the past stars of an owner or repository (owner/name) from the dates of the stars
+#[argh(subcommand, name = "backfill")]
+pub struct BackfillCommand {
+    #[argh(positional)]
+    pub name: String,
+    /// time between two reconstructed observations, eg `1d` or `1w` (default: 1d)
+    #[argh(option, default = "DurationArg(chrono::Duration::days(1))")]
+    pub step: DurationArg,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// start tracking who stars and unstars a repository (owner/name), or what a user stars
+#[argh(subcommand, name = "track")]
+pub struct TrackCommand {
+    #[argh(positional)]
+    pub name: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// stop tracking the stargazers of a repository (owner/name), or what a user stars
+#[argh(subcommand, name = "untrack")]
+pub struct UntrackCommand {
+    #[argh(positional)]
+    pub name: String,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// list the new and departed stargazers of a tracked repository (owner/name)
+#[argh(subcommand, name = "who")]
+pub struct WhoCommand {
+    #[argh(positional)]
+    pub name: String,
+    /// how far to look back, eg `7d` (default: 7d)
+    #[argh(option, default = "DurationArg(chrono::Duration::days(7))")]
+    pub since: DurationArg,
+    /// output format: 'csv', 'json', 'ndjson' or 'markdown' (default: csv)
+    #[argh(option, default = "OutputFormat::Csv")]
+    pub format: OutputFormat,
 }
 
 /// An optional boolean for use in Argh
@@ -125,3 +283,25 @@ impl argh::FromArgValue for BoolArg {
         }
     }
 }
+
+/// one of the lists of changes written by `gaze` and `report`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feed {
+    /// the changes of the repositories
+    Changes,
+    /// the changes of the followers of the followed users
+    Follows,
+    /// the repositories starred by the tracked users
+    Starred,
+}
+
+impl argh::FromArgValue for Feed {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_ref() {
+            "changes" => Ok(Self::Changes),
+            "follows" => Ok(Self::Follows),
+            "starred" => Ok(Self::Starred),
+            _ => Err(format!("Illegal value: {:?}", value)),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::{
+    DateTime,
+    Duration,
+    NaiveDate,
+    TimeZone,
+    Utc,
+};
+
+/// A point in time for use in Argh, either a date (eg `2024-03-01`)
+/// or a RFC 3339 time (eg `2024-03-01T12:00:00Z`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateArg {
+    Date(NaiveDate),
+    Time(DateTime<Utc>),
+}
+
+impl DateArg {
+    /// the time, a date meaning the start of its day
+    pub fn start(self) -> DateTime<Utc> {
+        match self {
+            Self::Date(date) => Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()), // SAFETY: valid time
+            Self::Time(time) => time,
+        }
+    }
+    /// the time, a date meaning the end of its day
+    pub fn end(self) -> DateTime<Utc> {
+        match self {
+            Self::Date(_) => self.start() + Duration::days(1) - Duration::seconds(1),
+            Self::Time(time) => time,
+        }
+    }
+}
+
+impl argh::FromArgValue for DateArg {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            return Ok(Self::Date(date));
+        }
+        DateTime::parse_from_rfc3339(value)
+            .map(|time| Self::Time(time.with_timezone(&Utc)))
+            .map_err(|_| {
+                format!(
+                    "Illegal date: {:?} (expected eg 2024-03-01 or 2024-03-01T12:00:00Z)",
+                    value
+                )
+            })
+    }
+}
@@ -0,0 +1,44 @@
+use chrono::Duration;
+
+/// A duration for use in Argh, made of a number and a unit,
+/// eg `30m`, `12h`, `7d` or `2w`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DurationArg(pub Duration);
+
+impl DurationArg {
+    pub fn value(self) -> Duration {
+        self.0
+    }
+}
+
+impl argh::FromArgValue for DurationArg {
+    fn from_arg_value(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let unit_idx = value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len());
+        let (number, unit) = value.split_at(unit_idx);
+        let number: i64 = number
+            .parse()
+            .map_err(|_| format!("Illegal duration: {:?}", value))?;
+        let unit_seconds = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => {
+                return Err(format!(
+                    "Illegal duration unit in {:?} (expected s, m, h, d or w)",
+                    value
+                ));
+            }
+        };
+        let duration = number
+            .checked_mul(unit_seconds)
+            .filter(|&seconds| seconds <= Duration::max_value().num_seconds())
+            .map(Duration::seconds)
+            .ok_or_else(|| format!("Duration too long: {:?}", value))?;
+        Ok(DurationArg(duration))
+    }
+}
@@ -1,24 +1,59 @@
 mod args;
+mod date_arg;
+mod duration_arg;
+mod watch;
 
-pub use args::*;
+pub use {
+    args::*,
+    date_arg::*,
+    duration_arg::*,
+    watch::*,
+};
 
 use {
     crate::*,
     anyhow::*,
+    chrono::{
+        Duration,
+        Local,
+        Utc,
+    },
     cli_log::*,
-    std::io,
+    std::{
+        io,
+        path::PathBuf,
+    },
     termimad::crossterm::tty::IsTty,
 };
 
 pub async fn run() -> Result<()> {
-    let args: Args = argh::from_env();
+    let mut args: Args = argh::from_env();
     debug!("args: {:#?}", &args);
     if args.version {
         println!("starry {}", env!("CARGO_PKG_VERSION"));
         return Ok(());
     }
-    let mut conf = Conf::read()?;
-    match args.command {
+    let mut conf = match args.config.take() {
+        Some(path) => Conf::read_at(path)?,
+        None => Conf::read()?,
+    };
+    let data_dir = match args.data_dir.take() {
+        Some(dir) => dir,
+        None => Db::default_dir()?,
+    };
+    match args.command.take() {
+        Some(ArgsCommand::Gaze(GazeCommand {
+            format: None,
+            feed: Some(_),
+            ..
+        }))
+        | Some(ArgsCommand::Report(ReportCommand {
+            format: None,
+            feed: Some(_),
+            ..
+        })) => {
+            bail!("--feed is only for formatted output");
+        }
         Some(ArgsCommand::Set(SetCommand { name, value })) => {
             conf.set(name, value);
             if !args.no_save {
@@ -34,11 +69,22 @@ pub async fn run() -> Result<()> {
             }
         },
         Some(ArgsCommand::Check(CheckCommand { name })) => {
-            UserId::new(name).check_on_github(&conf).await?;
+            if let Some(repo_id) = RepoId::parse(&name) {
+                repo_id.check_on_forge(&conf).await?;
+            } else {
+                UserId::check_on_forge(&name, &conf).await?;
+            }
         }
         Some(ArgsCommand::Follow(FollowCommand { name })) => {
-            if UserId::new(name.clone()).check_on_github(&conf).await? {
-                conf.follow(name);
+            if let Some(repo_id) = RepoId::parse(&name) {
+                if repo_id.check_on_forge(&conf).await? {
+                    conf.follow_repo(&repo_id);
+                    if !args.no_save {
+                        conf.save()?;
+                    }
+                }
+            } else if let Some(user_id) = UserId::check_on_forge(&name, &conf).await? {
+                conf.follow(user_id);
                 if !args.no_save {
                     conf.save()?;
                 }
@@ -50,37 +96,330 @@ pub async fn run() -> Result<()> {
                 conf.save()?;
             }
         }
-        Some(ArgsCommand::Extract(ExtractCommand { names })) => {
-            let db = Db::new()?;
-            let extract = Extract::read(&db, names)?;
-            extract.write_csv(&mut io::stdout())?;
+        Some(ArgsCommand::Extract(ExtractCommand {
+            names,
+            metric,
+            heartbeats,
+            format,
+        })) => {
+            let db = Db::at(&conf, data_dir)?;
+            let extract = Extract::read(&db, names, metric, heartbeats)?;
+            extract.write(format, &mut io::stdout())?;
+        }
+        Some(ArgsCommand::Graph(GraphCommand {
+            names,
+            metric,
+            heartbeats,
+            height,
+        })) => {
+            if names.is_empty() {
+                bail!("Give at least one user or repository to draw");
+            }
+            let db = Db::at(&conf, data_dir)?;
+            let extract = Extract::read(&db, names, metric, heartbeats)?;
+            let color = args
+                .color
+                .value()
+                .unwrap_or_else(|| std::io::stdout().is_tty());
+            let skin = make_skin(color);
+            Graph::new(&extract).print(&skin, color, height);
+        }
+        Some(ArgsCommand::Gaps(GapsCommand { logins, min })) => {
+            let db = Db::at(&conf, data_dir)?;
+            let user_ids = if logins.is_empty() {
+                db.user_ids()?
+            } else {
+                logins.iter().map(|login| UserId::parse(login)).collect()
+            };
+            let gaps = Gaps::read(&db, &user_ids, min.value())?;
+            gaps.write_csv(&mut io::stdout())?;
         }
-        Some(ArgsCommand::List(ListCommand { login })) => {
-            let db = Db::new()?;
+        Some(ArgsCommand::List(ListCommand {
+            login,
+            metric,
+            format,
+        })) => {
+            let db = Db::at(&conf, data_dir)?;
             let list = match login {
                 Some(login) => {
-                    let uo = db.last_user_obs(&UserId::new(&login))?;
+                    let uo = db.last_user_obs(&UserId::parse(&login))?;
                     match uo {
-                        Some(uo) => uo.into(),
+                        Some(uo) => List::repos(uo, metric),
                         None => bail!("no data for {:?}", login),
                     }
                 }
-                None => List::users(&db, &conf, false)?,
+                None => List::users(&db, &conf, false, metric)?,
+            };
+            list.write(format, &mut io::stdout())?;
+        }
+        Some(ArgsCommand::Migrate(MigrateCommand { from, to })) => {
+            if from.shares_files_with(to) {
+                bail!("{from} and {to} storages use the same files");
+            }
+            let db = Db::at(&conf, data_dir)?;
+            let source = from.open(&db.dir)?;
+            let target = to.open(&db.dir)?;
+            let report = migrate(&*source, &*target, !args.no_save, args.verbose)?;
+            report.print();
+            if !report.is_ok() {
+                bail!("{} problems during migration", report.problems.len());
+            }
+            if !args.no_save && conf.get(STORAGE_KEY) != Some(&to.to_string()) {
+                println!("You may now switch to this storage with `starry set {STORAGE_KEY} {to}`");
+            }
+        }
+        Some(ArgsCommand::Watch(WatchCommand { interval })) => {
+            let mut db = Db::at(&conf, data_dir)?;
+            db.verbose = args.verbose;
+            db.read_only = args.no_save;
+            watch(&db, conf, interval.value()).await?;
+        }
+        Some(ArgsCommand::Report(ReportCommand {
+            since,
+            from,
+            to,
+            format,
+            feed,
+        })) => {
+            let to = to.map_or_else(Utc::now, DateArg::end);
+            let from = match (since, from) {
+                (Some(_), Some(_)) => bail!("--since and --from can't be both given"),
+                (Some(since), None) => to
+                    .checked_sub_signed(since.value())
+                    .ok_or_else(|| anyhow!("--since is too far in the past"))?,
+                (None, Some(from)) => from.start(),
+                (None, None) => to - Duration::days(7),
+            };
+            if from >= to {
+                bail!("The period must end after its start");
+            }
+            let db = Db::at(&conf, data_dir)?;
+            let owners = conf.stored_owners();
+            let mut changes = db.changes_between(&owners, from, to)?;
+            let follow_changes = db.follow_changes_between(&owners, from, to)?;
+            let starred = db.starred_between(&owners, from, to)?;
+            if format.is_none() {
+                println!(
+                    "From {} to {}",
+                    from.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                    to.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                );
+            }
+            // the note goes to stderr when the output is meant for a program
+            for (user_id, times) in db.reconstructed_ends(&owners, from, to)? {
+                let times: Vec<String> = times
+                    .iter()
+                    .map(|time| {
+                        time.with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M")
+                            .to_string()
+                    })
+                    .collect();
+                let note = format!(
+                    "Note: the observations of {} at {} were reconstructed from the dates of the stars",
+                    user_id,
+                    times.join(" and "),
+                );
+                if format.is_none() {
+                    println!("{}", note);
+                } else {
+                    eprintln!("{}", note);
+                }
+            }
+            print_changes(
+                &mut changes,
+                &follow_changes,
+                &starred,
+                &conf,
+                &args,
+                format,
+                feed,
+            )?;
+        }
+        Some(ArgsCommand::Backfill(BackfillCommand { name, step })) => {
+            let mut db = Db::at(&conf, data_dir)?;
+            db.verbose = args.verbose;
+            db.read_only = args.no_save;
+            let report = backfill(&db, &conf, &name, step.value()).await?;
+            println!(
+                "{} reconstructed observations of {} written",
+                report.written, report.owner,
+            );
+            if !report.failed_repos.is_empty() {
+                bail!(
+                    "The stars of {} repositories couldn't be read",
+                    report.failed_repos.len()
+                );
+            }
+        }
+        Some(ArgsCommand::Track(TrackCommand { name })) => match RepoId::parse(&name) {
+            Some(repo_id) => {
+                if repo_id.check_on_forge(&conf).await? {
+                    if !conf.covers_repo(&repo_id) {
+                        conf.follow_repo(&repo_id);
+                    }
+                    conf.tracked_stargazers.insert(repo_id.to_string());
+                    if !args.no_save {
+                        conf.save()?;
+                    }
+                    println!(
+                        "The stargazers of {} will be fetched at each update",
+                        repo_id
+                    );
+                }
+            }
+            None => {
+                let mut user_id = conf.owner_id(&name);
+                let forge = forge_client(&conf, user_id.host.as_deref())?;
+                user_id.kind = forge.get_owner_kind(&user_id.login).await?;
+                if user_id.kind == OwnerKind::Organization {
+                    bail!(
+                        "{} is an organization, only users star repositories",
+                        user_id
+                    );
+                }
+                if !conf.watched_users.contains(&user_id.to_string()) {
+                    conf.follow(user_id.clone());
+                }
+                conf.starred_feeds.insert(user_id.to_string());
+                if !args.no_save {
+                    conf.save()?;
+                }
+                println!(
+                    "The repositories starred by {} will be fetched at each update",
+                    user_id
+                );
+            }
+        },
+        Some(ArgsCommand::Untrack(UntrackCommand { name })) => {
+            conf.tracked_stargazers.remove(&name);
+            conf.starred_feeds.remove(&name);
+            if !args.no_save {
+                conf.save()?;
+            }
+        }
+        Some(ArgsCommand::Who(WhoCommand {
+            name,
+            since,
+            format,
+        })) => {
+            let Some(repo_id) = RepoId::parse(&name) else {
+                bail!("Expected a repository, as owner/name");
             };
-            list.write_csv(&mut io::stdout())?;
+            let db = Db::at(&conf, data_dir)?;
+            let events = db.stargazer_events(&repo_id)?;
+            if events.is_empty() && !conf.tracked_stargazers.contains(&repo_id.to_string()) {
+                bail!(
+                    "The stargazers of {} aren't tracked. Use `starry track {}` first",
+                    repo_id,
+                    repo_id,
+                );
+            }
+            let start = Utc::now()
+                .checked_sub_signed(since.value())
+                .ok_or_else(|| anyhow!("--since is too far in the past"))?;
+            let events: Vec<StargazerEvent> =
+                events.into_iter().filter(|e| e.time >= start).collect();
+            stargazer_events_table(&events).write(format, &mut io::stdout())?;
+        }
+        Some(ArgsCommand::Gaze(GazeCommand {
+            format,
+            feed,
+            offline: true,
+        })) => {
+            let db = Db::at(&conf, data_dir)?;
+            let mut changes = db.last_changes(&conf.stored_owners())?;
+            print_changes(&mut changes, &[], &[], &conf, &args, format, feed)?;
+        }
+        Some(ArgsCommand::Gaze(GazeCommand { format, feed, .. })) => {
+            gaze(&conf, data_dir, &args, format, feed).await?;
+        }
+        None => {
+            gaze(&conf, data_dir, &args, None, None).await?;
+        }
+    }
+    Ok(())
+}
+
+/// fetch the stars, store them, and print the changes, either as
+/// a report or in the given format
+async fn gaze(
+    conf: &Conf,
+    data_dir: PathBuf,
+    args: &Args,
+    format: Option<OutputFormat>,
+    feed: Option<Feed>,
+) -> Result<()> {
+    let mut db = Db::at(conf, data_dir)?;
+    db.verbose = args.verbose;
+    db.read_only = args.no_save;
+    let mut changes = db.update(conf).await?;
+    // the observations are stored: from now on, failing would lose
+    // the changes, so they're notified first, and the errors of the
+    // other feeds are only logged
+    notify(conf, &changes).await;
+    let owners = conf.watched_owners();
+    let follow_changes = db.update_follows(conf, &owners).await.unwrap_or_else(|e| {
+        eprintln!("Error reading followers: {:?}", e);
+        Vec::new()
+    });
+    let starred = db.update_starred(conf, &owners).await.unwrap_or_else(|e| {
+        eprintln!("Error reading starred repositories: {:?}", e);
+        Vec::new()
+    });
+    print_changes(
+        &mut changes,
+        &follow_changes,
+        &starred,
+        conf,
+        args,
+        format,
+        feed,
+    )
+}
+
+/// print the changes, most interesting first, followed by the changes
+/// in followers and the repositories starred by the followed users,
+/// either as a report or in the given format. Only JSON holds all the
+/// lists, the other formats hold one of them, the changes by default
+fn print_changes(
+    changes: &mut [RepoChange],
+    follow_changes: &[FollowChange],
+    starred: &[StarredFeedItem],
+    conf: &Conf,
+    args: &Args,
+    format: Option<OutputFormat>,
+    feed: Option<Feed>,
+) -> Result<()> {
+    changes.sort_by(|a, b| b.interest().partial_cmp(&a.interest()).unwrap());
+    let table = |feed| match feed {
+        Feed::Changes => changes_table(changes, conf),
+        Feed::Follows => follow_changes_table(follow_changes),
+        Feed::Starred => starred_table(starred, conf),
+    };
+    match (format, feed) {
+        // only JSON holds several lists
+        (Some(OutputFormat::Json), None) => {
+            let sections = [
+                ("changes", table(Feed::Changes)),
+                ("follow_changes", table(Feed::Follows)),
+                ("starred", table(Feed::Starred)),
+            ];
+            write_json_sections(&sections, &mut io::stdout())?;
         }
-        Some(ArgsCommand::Gaze { .. }) | None => {
+        (Some(format), feed) => {
+            table(feed.unwrap_or(Feed::Changes)).write(format, &mut io::stdout())?;
+        }
+        (None, _) => {
             let color = args
                 .color
                 .value()
                 .unwrap_or_else(|| std::io::stdout().is_tty());
             let skin = make_skin(color);
-            let mut db = Db::new()?;
-            db.verbose = args.verbose;
-            db.read_only = args.no_save;
-            let mut changes = db.update(&conf).await?;
-            changes.sort_by(|a, b| b.interest().partial_cmp(&a.interest()).unwrap());
-            let report = ChangeReport::new(&changes, args.max_rows);
+            let report = ChangeReport::new(changes, conf, args.max_rows);
+            report.print(&skin);
+            FollowReport::new(follow_changes).print(&skin);
+            let report = StarredReport::new(starred, conf, args.max_rows);
             report.print(&skin);
         }
     }
@@ -0,0 +1,129 @@
+use {
+    crate::*,
+    anyhow::{
+        bail,
+        Result,
+    },
+    chrono::{
+        Duration,
+        Local,
+    },
+    cli_log::*,
+    tokio::time::{
+        sleep_until,
+        Instant,
+    },
+};
+
+/// a part of the followed owners and repositories, updated
+/// at its own time in the interval
+struct Slot {
+    owners: Vec<UserId>,
+    repo_ids: Vec<RepoId>,
+}
+
+/// split the followed owners and repositories in slots, one per owner
+/// and one for all the repositories followed on their own
+fn slots(conf: &Conf) -> Vec<Slot> {
+    let mut owners = conf.watched_owners();
+    owners.sort_by_key(|owner| owner.to_string());
+    let mut slots: Vec<Slot> = owners
+        .into_iter()
+        .map(|owner| Slot {
+            owners: vec![owner],
+            repo_ids: Vec::new(),
+        })
+        .collect();
+    let repo_ids = conf.watched_repo_ids();
+    if !repo_ids.is_empty() {
+        slots.push(Slot {
+            owners: Vec::new(),
+            repo_ids,
+        });
+    }
+    slots
+}
+
+/// update the database forever, every owner being fetched once per
+/// interval, at times spread over the interval.
+///
+/// The configuration is read again at every cycle, so that
+/// followed owners may be changed without restarting. The forge
+/// clients, and their API budgets, are kept from one cycle to the
+/// next: a change of host or token needs a restart.
+pub async fn watch(
+    db: &Db,
+    mut conf: Conf,
+    interval: Duration,
+) -> Result<()> {
+    if interval <= Duration::zero() {
+        bail!("The interval must be positive");
+    }
+    let interval = interval.to_std()?;
+    if Instant::now().checked_add(interval).is_none() {
+        bail!("The interval is too long");
+    }
+    loop {
+        let cycle_start = Instant::now();
+        if let Some(path) = conf.file_path.clone() {
+            match Conf::read_at(path) {
+                Ok(new_conf) => conf = new_conf,
+                Err(e) => eprintln!("Error reading the configuration: {:?}", e),
+            }
+        }
+        let slots = slots(&conf);
+        if slots.is_empty() {
+            eprintln!(
+                "Nothing followed. Use `starry follow some_name` to add a user or a repository."
+            );
+        }
+        let spacing = interval / slots.len().max(1) as u32;
+        for (idx, slot) in slots.into_iter().enumerate() {
+            sleep_until(cycle_start + spacing * idx as u32).await;
+            let owners = slot.owners.clone();
+            match db.update_some(&conf, slot.owners, slot.repo_ids).await {
+                Ok(changes) => {
+                    let time = Local::now().format("%Y-%m-%d %H:%M:%S");
+                    for change in &changes {
+                        let line = match change.url(&conf) {
+                            Ok(url) => format!("{} - {}", change, url),
+                            Err(_) => change.to_string(),
+                        };
+                        info!("{}", line);
+                        println!("{} {}", time, line);
+                    }
+                    notify(&conf, &changes).await;
+                    match db.update_follows(&conf, &owners).await {
+                        Ok(follow_changes) => {
+                            for change in &follow_changes {
+                                info!("{}", change);
+                                println!("{} {}", time, change);
+                            }
+                        }
+                        Err(e) => eprintln!("Error reading followers: {:?}", e),
+                    }
+                    match db.update_starred(&conf, &owners).await {
+                        Ok(items) => {
+                            for item in &items {
+                                let mut line =
+                                    format!("{} starred {}", item.user_id, item.starred.repo);
+                                if let Some(url) = item.url(&conf) {
+                                    line.push_str(&format!(" - {}", url));
+                                }
+                                info!("{}", line);
+                                println!("{} {}", time, line);
+                            }
+                        }
+                        Err(e) => eprintln!("Error reading starred repositories: {:?}", e),
+                    }
+                }
+                Err(e) => {
+                    // the next cycle will try again
+                    warn!("update failed: {:?}", e);
+                    eprintln!("Error during update: {:?}", e);
+                }
+            }
+        }
+        sleep_until(cycle_start + interval).await;
+    }
+}
@@ -19,28 +19,58 @@ use {
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Conf {
     pub props: HashMap<String, String>,
+    /// logins of the followed owners, users or organizations,
+    /// qualified with `@host` when they're not on github.com
     pub watched_users: HashSet<String>,
+    /// the followed owners which are organizations
+    #[serde(default)]
+    pub watched_organizations: HashSet<String>,
+    /// repositories followed independently of their owner, as `owner/name`
+    #[serde(default)]
+    pub watched_repos: HashSet<String>,
+    /// repositories whose stargazers are tracked, as `owner/name`
+    #[serde(default)]
+    pub tracked_stargazers: HashSet<String>,
+    /// users whose newly starred repositories are fetched at updates
+    #[serde(default)]
+    pub starred_feeds: HashSet<String>,
+    /// where the conf was read from, and will be saved
+    #[serde(skip)]
+    pub file_path: Option<PathBuf>,
 }
 
 impl Conf {
+    /// the standard location of the configuration (in
+    /// STARRY_HOME when it's set)
     pub fn path() -> Result<PathBuf> {
-        app_dirs().map(|dirs| dirs.config_dir().join("config.json"))
+        config_dir().map(|dir| dir.join("config.json"))
     }
     /// read the configuration from its standard location
     /// or return the default
     pub fn read() -> Result<Self> {
-        let path = Self::path()?;
-        if path.exists() {
+        Self::read_at(Self::path()?)
+    }
+    /// read the configuration from the given file, or return the
+    /// default if there's no such file. It will be saved there.
+    pub fn read_at(path: PathBuf) -> Result<Self> {
+        let mut conf = if path.exists() {
             let file_content = fs::read_to_string(&path)?;
-            Ok(serde_json::from_str(&file_content)?)
+            serde_json::from_str(&file_content)?
         } else {
-            Ok(Self::default())
-        }
+            Self::default()
+        };
+        conf.file_path = Some(path);
+        Ok(conf)
     }
-    /// write the conf at its standard location
+    /// write the conf where it was read, or at its standard location
     pub fn save(&self) -> Result<()> {
-        let path = Self::path()?;
-        fs::create_dir_all(path.parent().unwrap())?;
+        let path = match &self.file_path {
+            Some(path) => path.clone(),
+            None => Self::path()?,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
         let mut file = fs::File::create(&path)?;
         let json = serde_json::to_string_pretty(self)?;
         write!(&mut file, "{}", json)?;
@@ -60,10 +90,100 @@ impl Conf {
     ) -> Option<&str> {
         self.props.get(name).map(|s| s.as_str())
     }
+    /// return the metrics to fetch in addition to stars
+    pub fn metrics(&self) -> Result<Vec<Metric>> {
+        let metrics = self
+            .get(METRICS_KEY)
+            .map_or(Ok(Vec::new()), Metric::parse_list)?;
+        Ok(metrics
+            .into_iter()
+            .filter(|&metric| metric != Metric::Stars)
+            .collect())
+    }
+    /// return the forge instance with the given name,
+    /// or github.com when there's no name
+    pub fn host(
+        &self,
+        name: Option<&str>,
+    ) -> Result<Host> {
+        Host::from_conf(self, name)
+    }
+    /// return the id of an owner, with its kind as stored in conf
+    pub fn owner_id(
+        &self,
+        login: &str,
+    ) -> UserId {
+        let mut user_id = UserId::parse(login);
+        if self.watched_organizations.contains(login) {
+            user_id.kind = OwnerKind::Organization;
+        }
+        user_id
+    }
+    /// return the ids of all followed owners
+    pub fn watched_owners(&self) -> Vec<UserId> {
+        self.watched_users
+            .iter()
+            .map(|login| self.owner_id(login))
+            .collect()
+    }
+    /// return the ids of the repositories followed independently
+    /// of their owner (repositories of followed owners are excluded)
+    pub fn watched_repo_ids(&self) -> Vec<RepoId> {
+        self.watched_repos
+            .iter()
+            .filter_map(|name| RepoId::parse(name))
+            .filter(|repo_id| !self.watched_users.contains(&repo_id.owner.to_string()))
+            .collect()
+    }
+    /// return the ids of the owners whose observations are stored:
+    /// followed owners, and owners of independently followed repositories
+    pub fn stored_owners(&self) -> Vec<UserId> {
+        let mut owners = self.watched_owners();
+        for repo_id in self.watched_repo_ids() {
+            if !owners
+                .iter()
+                .any(|o| o.to_string() == repo_id.owner.to_string())
+            {
+                owners.push(repo_id.owner);
+            }
+        }
+        owners
+    }
+    /// return the ids of the repositories whose stargazers are tracked
+    pub fn tracked_stargazer_repo_ids(&self) -> Vec<RepoId> {
+        let mut repo_ids: Vec<RepoId> = self
+            .tracked_stargazers
+            .iter()
+            .filter_map(|name| RepoId::parse(name))
+            .collect();
+        repo_ids.sort_by_key(|repo_id| repo_id.to_string());
+        repo_ids
+    }
+    /// tell whether the stars of the repository are fetched at
+    /// updates, because it or its owner is followed
+    pub fn covers_repo(
+        &self,
+        repo_id: &RepoId,
+    ) -> bool {
+        self.watched_users.contains(&repo_id.owner.to_string())
+            || self.watched_repos.contains(&repo_id.to_string())
+    }
+    pub fn follow_repo(
+        &mut self,
+        repo_id: &RepoId,
+    ) {
+        self.watched_repos.insert(repo_id.to_string());
+    }
     pub fn follow(
         &mut self,
-        name: String,
+        user_id: UserId,
     ) {
+        let name = user_id.to_string();
+        if user_id.kind == OwnerKind::Organization {
+            self.watched_organizations.insert(name.clone());
+        } else {
+            self.watched_organizations.remove(&name);
+        }
         self.watched_users.insert(name);
     }
     pub fn unfollow(
@@ -71,5 +191,9 @@ impl Conf {
         name: &str,
     ) {
         self.watched_users.remove(name);
+        self.watched_organizations.remove(name);
+        self.watched_repos.remove(name);
+        self.tracked_stargazers.remove(name);
+        self.starred_feeds.remove(name);
     }
 }
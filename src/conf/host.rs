@@ -0,0 +1,100 @@
+use {
+    crate::*,
+    anyhow::Result,
+    std::fmt,
+};
+
+/// the prop key for the URL of the GraphQL API of a host
+pub static API_URL_KEY: &str = "api-url";
+
+/// the prop key for the base URL of the web pages of a host
+pub static WEB_URL_KEY: &str = "web-url";
+
+/// the prop key for the API token of a host which isn't a GitHub one
+pub static API_TOKEN_KEY: &str = "api-token";
+
+static DEFAULT_HOST_NAME: &str = "github.com";
+static DEFAULT_API_URL: &str = "https://api.github.com/graphql";
+static DEFAULT_WEB_URL: &str = "https://github.com/";
+
+/// a forge instance: either github.com (when there's no name)
+/// or a named one: GitHub Enterprise Server, GitLab, Gitea, or
+/// any stand-in.
+///
+/// Props of a named host are suffixed with `@name`, for
+/// example `api-url@ghe.example.com`. A named host without
+/// specific URL is assumed to follow the conventions of its forge,
+/// which is GitHub unless the host is well known or there's
+/// a `forge@name` prop.
+#[derive(Debug, Clone)]
+pub struct Host {
+    pub name: Option<String>,
+    pub forge: ForgeKind,
+    pub api_url: String,
+    /// base of the web URLs, ending with a slash
+    pub web_url: String,
+    pub token: Option<String>,
+}
+
+/// return the key of a prop specific to a host
+pub fn host_prop_key(
+    key: &str,
+    host: Option<&str>,
+) -> String {
+    match host {
+        Some(host) => format!("{}@{}", key, host),
+        None => key.to_string(),
+    }
+}
+
+impl Host {
+    pub fn from_conf(
+        conf: &Conf,
+        name: Option<&str>,
+    ) -> Result<Self> {
+        let prop = |key: &str| conf.get(&host_prop_key(key, name)).map(|s| s.to_string());
+        let forge = match (prop(FORGE_KEY), name) {
+            (Some(forge), _) => forge.parse()?,
+            (None, Some(name)) => ForgeKind::of_known_host(name).unwrap_or_default(),
+            (None, None) => ForgeKind::Github,
+        };
+        let api_url = prop(API_URL_KEY).unwrap_or_else(|| match name {
+            Some(name) => forge.default_api_url(name),
+            None => DEFAULT_API_URL.to_string(),
+        });
+        let mut web_url = prop(WEB_URL_KEY).unwrap_or_else(|| match name {
+            Some(name) => format!("https://{}/", name),
+            None => DEFAULT_WEB_URL.to_string(),
+        });
+        if !web_url.ends_with('/') {
+            web_url.push('/');
+        }
+        let token = match forge {
+            ForgeKind::Github => prop(GITHUB_API_TOKEN_KEY),
+            _ => prop(API_TOKEN_KEY),
+        };
+        Ok(Self {
+            name: name.map(|s| s.to_string()),
+            forge,
+            api_url,
+            web_url,
+            token,
+        })
+    }
+    /// the URL of the web page of a repository of this host
+    pub fn repo_url(
+        &self,
+        repo_id: &RepoId,
+    ) -> String {
+        format!("{}{}/{}", self.web_url, repo_id.owner.login, repo_id.name)
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{}", self.name.as_deref().unwrap_or(DEFAULT_HOST_NAME))
+    }
+}
@@ -1,3 +1,7 @@
 mod conf;
+mod host;
 
-pub use conf::*;
+pub use {
+    conf::*,
+    host::*,
+};
@@ -0,0 +1,152 @@
+use {
+    crate::*,
+    anyhow::{
+        bail,
+        Result,
+    },
+    chrono::{
+        DateTime,
+        Duration,
+        SubsecRound,
+        TimeZone,
+        Utc,
+    },
+};
+
+/// what a backfill did
+#[derive(Debug)]
+pub struct BackfillReport {
+    pub owner: UserId,
+    /// the number of reconstructed observations written
+    pub written: usize,
+    /// the repositories whose history couldn't be read
+    pub failed_repos: Vec<String>,
+}
+
+/// synthesize the past observations of an owner from the star
+/// histories of its repositories, at most one every `step`, from
+/// the day of the first star to `until` (excluded).
+///
+/// Observations are only produced when they differ from the previous
+/// one. Their repository observations are flagged as reconstructed.
+pub fn reconstruct(
+    user_id: &UserId,
+    repos: &[(RepoObs, StarHistory)],
+    until: DateTime<Utc>,
+    step: Duration,
+) -> Vec<UserObs> {
+    let mut user_obss: Vec<UserObs> = Vec::new();
+    let first_star = repos
+        .iter()
+        .filter_map(|(_, history)| history.stars.first())
+        .map(|star| star.starred_at)
+        .min();
+    let Some(first_star) = first_star else {
+        return user_obss;
+    };
+    let first_day = first_star.date_naive().and_hms_opt(0, 0, 0).unwrap(); // SAFETY: valid time
+    let mut time = Utc.from_utc_datetime(&first_day);
+    while let Some(next) = time.checked_add_signed(step).filter(|&next| next < until) {
+        time = next;
+        let counts: Vec<RepoObs> = repos
+            .iter()
+            .filter(|(_, history)| match history.created_at {
+                Some(created_at) => created_at <= time,
+                None => history.stars_at(time) > 0,
+            })
+            .map(|(repo_obs, history)| RepoObs {
+                id: repo_obs.id.clone(),
+                reconstructed: Some(true),
+                ..RepoObs::new(&repo_obs.repo_name, history.stars_at(time))
+            })
+            .collect();
+        if user_obss.last().is_none_or(|last| last.counts != counts) {
+            user_obss.push(UserObs {
+                user_id: user_id.clone(),
+                time,
+                counts,
+            });
+        }
+    }
+    user_obss
+}
+
+/// reconstruct the past observations of an owner (or of just one of
+/// its repositories when the name is `owner/repo`) from the dates of
+/// the stars, and store the ones preceding the first stored observation.
+///
+/// Nothing is stored when the history of a repository couldn't be read.
+pub async fn backfill(
+    db: &Db,
+    conf: &Conf,
+    name: &str,
+    step: Duration,
+) -> Result<BackfillReport> {
+    if step <= Duration::zero() {
+        bail!("The step of a backfill must be positive");
+    }
+    let repo_id = RepoId::parse(name);
+    let mut owner = match &repo_id {
+        Some(repo_id) => repo_id.owner.clone(),
+        None => conf.owner_id(name),
+    };
+    let forge = db.forge_client(conf, &owner.host)?;
+    owner.kind = forge.get_owner_kind(&owner.login).await?;
+    let now = Utc::now().trunc_subsecs(0);
+    let current = match &repo_id {
+        Some(repo_id) => {
+            let repo_ids = vec![RepoId::new(owner.clone(), &repo_id.name)];
+            forge
+                .get_repos_star_counts(&repo_ids, now)
+                .await?
+                .into_iter()
+                .flat_map(|user_obs| user_obs.counts)
+                .collect()
+        }
+        None => forge.get_user_star_counts(owner.clone(), now).await?.counts,
+    };
+    if current.is_empty() {
+        bail!("Nothing to backfill for {}", name);
+    }
+    let until = db.user_obs_times(&owner)?.first().copied().unwrap_or(now);
+    let mut task = Task::new(format!("Read the stars of {} repositories", current.len()))
+        .with_total(current.len());
+    let mut repos = Vec::new();
+    let mut failed_repos = Vec::new();
+    for repo_obs in current {
+        let history = if repo_obs.stars > 0 {
+            let repo_id = RepoId::new(owner.clone(), &repo_obs.repo_name);
+            forge.get_star_history(&repo_id).await
+        } else {
+            Ok(StarHistory::default())
+        };
+        task.increment();
+        match history {
+            Ok(history) => repos.push((repo_obs, history)),
+            Err(e) => {
+                eprintln!("Error reading the stars of {}: {:?}", repo_obs.repo_name, e);
+                failed_repos.push(repo_obs.repo_name);
+            }
+        }
+    }
+    if !failed_repos.is_empty() {
+        // the failed repositories would look like they appeared later, and
+        // the next backfill couldn't fix it as it stops at the first observation
+        task.finish("Nothing reconstructed");
+        return Ok(BackfillReport {
+            owner,
+            written: 0,
+            failed_repos,
+        });
+    }
+    let user_obss = reconstruct(&owner, &repos, until, step);
+    task.finish(format!("Reconstructed {} observations", user_obss.len()));
+    for user_obs in &user_obss {
+        db.store(user_obs)?;
+    }
+    Ok(BackfillReport {
+        owner,
+        written: user_obss.len(),
+        failed_repos,
+    })
+}
@@ -1,13 +1,50 @@
 use {
     crate::*,
+    anyhow::Result,
+    serde::Serialize,
     std::fmt,
 };
 
-#[derive(Debug)]
+/// what happened to a repository
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind {
+    /// the number of stars changed, or the repository is new
+    Stars,
+    /// the repository was renamed, it was previously known with this name
+    Renamed { old_name: String },
+    /// the repository was transferred from another owner
+    Transferred { from: RepoId },
+    /// the repository was archived
+    Archived,
+    /// the repository can't be seen anymore: it was deleted,
+    /// made private, or transferred to an unknown place
+    Disappeared,
+}
+
+#[derive(Debug, Clone)]
 pub struct RepoChange {
     pub repo_id: RepoId,
+    pub kind: ChangeKind,
+    /// the stable id of the repository, when known
+    pub id: Option<String>,
+    pub old_stars: Option<usize>,
+    /// the current number of stars, none when the repository disappeared
+    pub new_stars: Option<usize>,
+}
+
+/// a change, with all that's needed to understand it outside of
+/// starry (in JSON, CSV, webhooks, etc.)
+#[derive(Debug, Serialize)]
+pub struct ChangeRecord {
+    pub owner: String,
+    pub repo: String,
+    /// what happened, when it's more than a change of stars
+    pub event: Option<String>,
     pub old_stars: Option<usize>,
-    pub new_stars: usize,
+    pub new_stars: Option<usize>,
+    pub value: f64,
+    pub interest: f64,
+    pub url: String,
 }
 
 impl fmt::Display for RepoChange {
@@ -17,37 +54,98 @@ impl fmt::Display for RepoChange {
     ) -> fmt::Result {
         //write!(f, "[{}] ", self.value())?;
         write!(f, "{}", self.repo_id)?;
-        if let Some(old_stars) = self.old_stars {
-            if old_stars < self.new_stars {
-                write!(f, " rised from {} to {}", old_stars, self.new_stars)?;
-            } else {
-                write!(f, " dropped from {} to {}", old_stars, self.new_stars)?;
+        if let Some(event) = self.event() {
+            write!(f, " {}", event)?;
+        }
+        match (self.old_stars, self.new_stars) {
+            (Some(old_stars), Some(new_stars)) if old_stars < new_stars => {
+                write!(f, " rised from {} to {}", old_stars, new_stars)?;
             }
-        } else if self.new_stars > 1 {
-            write!(f, " is new and has already {} stars", self.new_stars)?;
-        } else {
-            write!(f, " is new")?;
+            (Some(old_stars), Some(new_stars)) if old_stars > new_stars => {
+                write!(f, " dropped from {} to {}", old_stars, new_stars)?;
+            }
+            (Some(old_stars), None) => {
+                write!(f, " had {} stars", old_stars)?;
+            }
+            (None, Some(new_stars)) if new_stars > 1 => {
+                write!(f, " is new and has already {} stars", new_stars)?;
+            }
+            (None, _) => {
+                write!(f, " is new")?;
+            }
+            _ => {}
         }
-        write!(f, " - https://github.com/{}", self.repo_id)
+        Ok(())
     }
 }
 
 impl RepoChange {
-    pub fn url(&self) -> String {
-        format!("https://github.com/{}", self.repo_id)
+    /// a change of the number of stars (or a new repo)
+    pub fn stars(
+        repo_id: RepoId,
+        old_stars: Option<usize>,
+        new_stars: usize,
+    ) -> Self {
+        Self {
+            repo_id,
+            kind: ChangeKind::Stars,
+            id: None,
+            old_stars,
+            new_stars: Some(new_stars),
+        }
+    }
+    /// the URL of the repository's page, on its host
+    pub fn url(
+        &self,
+        conf: &Conf,
+    ) -> Result<String> {
+        let host = conf.host(self.repo_id.owner.host.as_deref())?;
+        Ok(host.repo_url(&self.repo_id))
+    }
+    pub fn record(
+        &self,
+        conf: &Conf,
+    ) -> ChangeRecord {
+        ChangeRecord {
+            owner: self.repo_id.owner.to_string(),
+            repo: self.repo_id.name.clone(),
+            event: self.event(),
+            old_stars: self.old_stars,
+            new_stars: self.new_stars,
+            value: self.value(),
+            interest: self.interest(),
+            url: self.url(conf).unwrap_or_default(),
+        }
+    }
+    /// describe what happened, when it's more than a change of stars
+    pub fn event(&self) -> Option<String> {
+        match &self.kind {
+            ChangeKind::Stars => None,
+            ChangeKind::Renamed { old_name } => Some(format!("renamed from {}", old_name)),
+            ChangeKind::Transferred { from } => Some(format!("transferred from {}", from)),
+            ChangeKind::Archived => Some("archived".to_string()),
+            ChangeKind::Disappeared => Some("disappeared".to_string()),
+        }
     }
     pub fn value(&self) -> f64 {
-        if let Some(old_stars) = self.old_stars {
-            let o = old_stars as f64;
-            let n = self.new_stars as f64;
-            100f64 * (n - o) / (100f64 + o + n)
-        } else {
-            0.2f64 + (self.new_stars as f64) / 20f64
+        match (self.old_stars, self.new_stars) {
+            (Some(old_stars), Some(new_stars)) => {
+                let o = old_stars as f64;
+                let n = new_stars as f64;
+                100f64 * (n - o) / (100f64 + o + n)
+            }
+            (None, Some(new_stars)) => 0.2f64 + (new_stars as f64) / 20f64,
+            (Some(old_stars), None) => -0.2f64 - (old_stars as f64) / 20f64,
+            (None, None) => 0f64,
         }
     }
     /// how much this change is interesting
     pub fn interest(&self) -> f64 {
-        self.value().abs()
+        match self.kind {
+            ChangeKind::Stars => self.value().abs(),
+            // other events are always worth a look
+            _ => self.value().abs() + 1f64,
+        }
     }
     pub fn trend_markdown(&self) -> &'static str {
         let value = self.value();
@@ -68,3 +166,67 @@ impl RepoChange {
         }
     }
 }
+
+/// the changes as a table, one row per change
+pub fn changes_table(
+    changes: &[RepoChange],
+    conf: &Conf,
+) -> Table {
+    let columns = [
+        "owner",
+        "repo",
+        "event",
+        "old_stars",
+        "new_stars",
+        "value",
+        "interest",
+        "url",
+    ];
+    let mut table = Table::new(columns.iter().map(|c| c.to_string()).collect());
+    for change in changes {
+        let record = change.record(conf);
+        table.add_row(vec![
+            record.owner.into(),
+            record.repo.into(),
+            record.event.into(),
+            record.old_stars.into(),
+            record.new_stars.into(),
+            record.value.into(),
+            record.interest.into(),
+            record.url.into(),
+        ]);
+    }
+    table
+}
+
+/// merge the disappearance of a repository and its appearance
+/// at another owner into a transfer
+pub fn detect_transfers(changes: &mut Vec<RepoChange>) {
+    let mut idx = 0;
+    while idx < changes.len() {
+        let disappeared = &changes[idx];
+        let target = match (&disappeared.kind, &disappeared.id) {
+            (ChangeKind::Disappeared, Some(id)) => changes.iter().position(|c| {
+                c.kind == ChangeKind::Stars
+                    && c.old_stars.is_none()
+                    && c.id.as_ref() == Some(id)
+                    && c.repo_id.owner.host == disappeared.repo_id.owner.host
+            }),
+            _ => None,
+        };
+        match target {
+            Some(target) => {
+                let disappeared = changes.remove(idx);
+                let target = if target > idx { target - 1 } else { target };
+                let change = &mut changes[target];
+                change.kind = ChangeKind::Transferred {
+                    from: disappeared.repo_id,
+                };
+                change.old_stars = disappeared.old_stars;
+            }
+            None => {
+                idx += 1;
+            }
+        }
+    }
+}
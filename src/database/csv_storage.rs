@@ -0,0 +1,438 @@
+use {
+    crate::*,
+    anyhow::{
+        bail,
+        Context,
+        Result,
+    },
+    chrono::{
+        DateTime,
+        Utc,
+    },
+    cli_log::*,
+    std::{
+        fs,
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+};
+
+static HEARTBEATS_FILENAME: &str = "heartbeats.csv";
+
+/// the suffix of the files of stargazer events, after the repository name
+static STARGAZERS_SUFFIX: &str = ".stargazers.csv";
+
+static STARRED_FILENAME: &str = "starred.csv";
+
+static FOLLOWS_FILENAME: &str = "follows.csv";
+
+/// the original storage: one CSV file per (user, time), in
+/// `stars/<login>/<rfc3339>.csv`.
+///
+/// In delta mode, most observations are written as the differences
+/// with the previous one (`<rfc3339>.delta.csv`), with a full
+/// observation (a keyframe) every `KEYFRAME_INTERVAL` files. Both
+/// kinds of files are read whatever the mode.
+///
+/// Heartbeats are appended to `stars/<login>/heartbeats.csv`, the
+/// numbers of followers to `stars/<login>/follows.csv`, stargazer
+/// events to `stars/<login>/<repo>.stargazers.csv`, and the
+/// repositories starred by the user to `stars/<login>/starred.csv`.
+#[derive(Debug)]
+pub struct CsvStorage {
+    /// the directory containing the `stars` directory
+    pub dir: PathBuf,
+    /// whether to write deltas instead of full observations
+    pub delta: bool,
+}
+
+impl CsvStorage {
+    pub fn new(
+        data_dir: &Path,
+        delta: bool,
+    ) -> Self {
+        Self {
+            dir: data_dir.to_path_buf(),
+            delta,
+        }
+    }
+    pub fn user_stars_dir(
+        &self,
+        user_id: &UserId,
+    ) -> PathBuf {
+        self.dir.join("stars").join(user_id.to_string())
+    }
+    /// return the sorted observation files of a user, or none
+    /// if there's no directory for this user
+    fn obs_files(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Option<Vec<ObsFile>>> {
+        let user_dir = self.user_stars_dir(user_id);
+        if !user_dir.exists() {
+            return Ok(None);
+        }
+        ObsFile::list(&user_dir).map(Some)
+    }
+    fn stargazers_path(
+        &self,
+        repo_id: &RepoId,
+    ) -> PathBuf {
+        self.user_stars_dir(&repo_id.owner)
+            .join(format!("{}{}", repo_id.name, STARGAZERS_SUFFIX))
+    }
+    /// replay all the observations of a user, in time order, calling
+    /// the given function on each one.
+    ///
+    /// Unreadable files are skipped with a warning. As deltas apply to
+    /// the previous file, the deltas following an unreadable file are
+    /// skipped too, until the next readable keyframe
+    fn replay_readable(
+        &self,
+        user_id: &UserId,
+        mut f: impl FnMut(&UserObs),
+    ) -> Result<()> {
+        let files = match self.obs_files(user_id)? {
+            Some(files) => files,
+            None => bail!("no data for user {}", user_id),
+        };
+        let mut user_obs = UserObs {
+            user_id: user_id.clone(),
+            time: Utc::now(),
+            counts: Vec::new(),
+        };
+        let mut broken = false;
+        for file in &files {
+            if broken && file.delta {
+                eprintln!("Skipping {:?}, which follows an unreadable file", file.path);
+                continue;
+            }
+            debug!("reading file {:?}", &file.path);
+            match file.apply_to(&mut user_obs) {
+                Err(e) => {
+                    eprintln!("Skipping unreadable file {:?}: {:?}", file.path, e);
+                    broken = true;
+                }
+                Ok(()) => {
+                    broken = false;
+                    f(&user_obs);
+                }
+            }
+        }
+        Ok(())
+    }
+    /// rebuild the observation of the given file by replaying the files
+    /// since the last keyframe (files must be sorted)
+    fn replay(
+        user_id: &UserId,
+        files: &[ObsFile],
+        idx: usize,
+    ) -> Result<UserObs> {
+        let start = files[..=idx].iter().rposition(|f| !f.delta).unwrap_or(0);
+        let mut user_obs = UserObs {
+            user_id: user_id.clone(),
+            time: files[idx].time,
+            counts: Vec::new(),
+        };
+        for file in &files[start..=idx] {
+            file.apply_to(&mut user_obs)
+                .with_context(|| format!("reading file {:?}", file.path))?;
+        }
+        Ok(user_obs)
+    }
+}
+
+impl Storage for CsvStorage {
+    fn last_user_obs(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Option<UserObs>> {
+        match self.obs_files(user_id)? {
+            Some(files) if !files.is_empty() => {
+                Self::replay(user_id, &files, files.len() - 1).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+    fn count_user_obs(
+        &self,
+        user_id: &UserId,
+    ) -> Result<usize> {
+        Ok(self.obs_files(user_id)?.map_or(0, |files| files.len()))
+    }
+    fn extract_user(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<DatedObs>> {
+        let mut dated_obss = Vec::new();
+        self.replay_readable(user_id, |user_obs| {
+            dated_obss.push(user_obs.sum());
+        })?;
+        Ok(dated_obss)
+    }
+    fn extract_user_query(
+        &self,
+        user_id: &UserId,
+        repo_names: Vec<&str>,
+        metric: Metric,
+    ) -> Result<Vec<UserResponseLine>> {
+        let mut lines = Vec::new();
+        self.replay_readable(user_id, |user_obs| {
+            lines.push(UserResponseLine {
+                time: user_obs.time,
+                sum: user_obs.sum_metric(metric),
+                counts: repo_names
+                    .iter()
+                    .map(|repo_name| {
+                        user_obs
+                            .repo(repo_name)
+                            .and_then(|repo_obs| repo_obs.get(metric))
+                    })
+                    .collect(),
+                reconstructed: user_obs.is_reconstructed(),
+            });
+        })?;
+        Ok(lines)
+    }
+    fn user_ids(&self) -> Result<Vec<UserId>> {
+        let stars_dir = self.dir.join("stars");
+        if !stars_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut user_ids = Vec::new();
+        for entry in fs::read_dir(stars_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                if let Some(login) = entry.file_name().to_str() {
+                    user_ids.push(UserId::parse(login));
+                }
+            }
+        }
+        Ok(user_ids)
+    }
+    fn user_obs_times(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<DateTime<Utc>>> {
+        Ok(self
+            .obs_files(user_id)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|file| file.time)
+            .collect())
+    }
+    fn read_user_obs(
+        &self,
+        user_id: &UserId,
+        time: DateTime<Utc>,
+    ) -> Result<UserObs> {
+        let files = self.obs_files(user_id)?.unwrap_or_default();
+        match files.iter().position(|file| file.time == time) {
+            Some(idx) => Self::replay(user_id, &files, idx),
+            None => bail!("no observation of {} at {}", user_id, time),
+        }
+    }
+    fn write_user_obs(
+        &self,
+        user_obs: &UserObs,
+        verbose: bool,
+    ) -> Result<()> {
+        let user_dir = self.user_stars_dir(&user_obs.user_id);
+        let files = self.obs_files(&user_obs.user_id)?.unwrap_or_default();
+        // a delta following the new observation was computed against
+        // the file before it: it's rewritten whole first
+        if let Some(next) = files.iter().position(|f| f.time > user_obs.time) {
+            if files[next].delta {
+                let next_user_obs = Self::replay(&user_obs.user_id, &files, next)?;
+                next_user_obs.write_in_dir(&user_dir, verbose)?;
+                fs::remove_file(&files[next].path)?;
+            }
+        }
+        if self.delta {
+            // we write a delta only after a readable keyframe, and
+            // if there aren't already too many deltas since it.
+            // Observations older than the last one (reconstructed ones)
+            // are written whole, as deltas apply to the previous file
+            let since_keyframe = files.iter().rev().take_while(|f| f.delta).count();
+            let is_last = files.last().is_none_or(|f| f.time < user_obs.time);
+            if is_last && since_keyframe < files.len() && since_keyframe < KEYFRAME_INTERVAL {
+                if let Ok(old_user_obs) = Self::replay(&user_obs.user_id, &files, files.len() - 1) {
+                    let deltas = user_obs.delta_from(&old_user_obs);
+                    return user_obs.write_delta_in_dir(&deltas, &user_dir, verbose);
+                }
+            }
+        }
+        user_obs.write_in_dir(&user_dir, verbose)
+    }
+    fn write_heartbeat(
+        &self,
+        user_id: &UserId,
+        heartbeat: &Heartbeat,
+    ) -> Result<()> {
+        let user_dir = self.user_stars_dir(user_id);
+        fs::create_dir_all(&user_dir)?;
+        let path = user_dir.join(HEARTBEATS_FILENAME);
+        let exists = path.exists();
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let mut w = csv::WriterBuilder::new()
+            .has_headers(!exists)
+            .from_writer(file);
+        w.serialize(heartbeat)?;
+        w.flush()?;
+        Ok(())
+    }
+    fn heartbeats(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<Heartbeat>> {
+        let path = self.user_stars_dir(user_id).join(HEARTBEATS_FILENAME);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut r = csv::Reader::from_path(&path)?;
+        let mut heartbeats = Vec::new();
+        for heartbeat in r.deserialize() {
+            heartbeats.push(heartbeat.with_context(|| format!("reading file {:?}", path))?);
+        }
+        heartbeats.sort_by_key(|h: &Heartbeat| h.time);
+        Ok(heartbeats)
+    }
+    fn write_follow_obs(
+        &self,
+        user_id: &UserId,
+        follow_obs: &FollowObs,
+    ) -> Result<()> {
+        let user_dir = self.user_stars_dir(user_id);
+        fs::create_dir_all(&user_dir)?;
+        let path = user_dir.join(FOLLOWS_FILENAME);
+        let exists = path.exists();
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let mut w = csv::WriterBuilder::new()
+            .has_headers(!exists)
+            .from_writer(file);
+        w.serialize(follow_obs)?;
+        w.flush()?;
+        Ok(())
+    }
+    fn follow_obss(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<FollowObs>> {
+        let path = self.user_stars_dir(user_id).join(FOLLOWS_FILENAME);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut r = csv::Reader::from_path(&path)?;
+        let mut follow_obss = Vec::new();
+        for follow_obs in r.deserialize() {
+            follow_obss.push(follow_obs.with_context(|| format!("reading file {:?}", path))?);
+        }
+        follow_obss.sort_by_key(|f: &FollowObs| f.time);
+        Ok(follow_obss)
+    }
+    fn write_stargazer_events(
+        &self,
+        repo_id: &RepoId,
+        events: &[StargazerEvent],
+    ) -> Result<()> {
+        let path = self.stargazers_path(repo_id);
+        fs::create_dir_all(self.user_stars_dir(&repo_id.owner))?;
+        let exists = path.exists();
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let mut w = csv::WriterBuilder::new()
+            .has_headers(!exists)
+            .from_writer(file);
+        for event in events {
+            w.serialize(event)?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+    fn stargazer_events(
+        &self,
+        repo_id: &RepoId,
+    ) -> Result<Vec<StargazerEvent>> {
+        let path = self.stargazers_path(repo_id);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut r = csv::Reader::from_path(&path)?;
+        let mut events = Vec::new();
+        for event in r.deserialize() {
+            events.push(event.with_context(|| format!("reading file {:?}", path))?);
+        }
+        events.sort_by_key(|e: &StargazerEvent| e.time);
+        Ok(events)
+    }
+    fn stargazer_repo_names(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<String>> {
+        let user_dir = self.user_stars_dir(user_id);
+        if !user_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in fs::read_dir(user_dir)? {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(STARGAZERS_SUFFIX));
+            if let Some(name) = name {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+    fn write_starred(
+        &self,
+        user_id: &UserId,
+        starred: &[StarredRepo],
+    ) -> Result<()> {
+        let user_dir = self.user_stars_dir(user_id);
+        fs::create_dir_all(&user_dir)?;
+        let path = user_dir.join(STARRED_FILENAME);
+        let exists = path.exists();
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let mut w = csv::WriterBuilder::new()
+            .has_headers(!exists)
+            .from_writer(file);
+        for starred_repo in starred {
+            w.serialize(starred_repo)?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+    fn starred(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<StarredRepo>> {
+        let path = self.user_stars_dir(user_id).join(STARRED_FILENAME);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut r = csv::Reader::from_path(&path)?;
+        let mut starred = Vec::new();
+        for starred_repo in r.deserialize() {
+            starred.push(starred_repo.with_context(|| format!("reading file {:?}", path))?);
+        }
+        starred.sort_by_key(|s: &StarredRepo| s.starred_at);
+        Ok(starred)
+    }
+}
@@ -1,17 +1,22 @@
 use {
     crate::*,
     anyhow::{
-        bail,
+        anyhow,
         Result,
     },
-    chrono::Utc,
+    chrono::{
+        DateTime,
+        SubsecRound,
+        Utc,
+    },
     cli_log::*,
     futures::stream::{
         self,
         StreamExt,
     },
     std::{
-        fs,
+        collections::HashMap,
+        fmt,
         path::PathBuf,
         sync::Arc,
     },
@@ -27,55 +32,90 @@ pub struct Db {
     pub verbose: bool,
     /// whether to save on disk
     pub read_only: bool,
+    /// the layout in which observations are stored
+    storage: Box<dyn Storage>,
+    /// the forge clients, by host, created at first use
+    clients: ForgeClients,
+}
+
+/// the clients of the forges, by host name.
+///
+/// All queries of a db to a host share the same client, so that
+/// they share the same API budget and concurrency limit.
+#[derive(Default)]
+struct ForgeClients(std::sync::Mutex<HashMap<Option<String>, Arc<dyn Forge>>>);
+
+impl fmt::Debug for ForgeClients {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        let hosts: Vec<Option<String>> = match self.0.lock() {
+            Ok(clients) => clients.keys().cloned().collect(),
+            Err(_) => Vec::new(),
+        };
+        f.debug_tuple("ForgeClients").field(&hosts).finish()
+    }
 }
 
 impl Db {
-    pub fn new() -> Result<Self> {
-        let dir = app_dirs()?.data_dir().to_path_buf();
+    /// the standard location of the database (STARRY_HOME
+    /// when it's set)
+    pub fn default_dir() -> Result<PathBuf> {
+        data_dir()
+    }
+    /// open the database at its standard location, with the
+    /// storage kind defined in conf
+    pub fn new(conf: &Conf) -> Result<Self> {
+        Self::at(conf, Self::default_dir()?)
+    }
+    /// open the database in the given directory, with the
+    /// storage kind defined in conf
+    pub fn at(
+        conf: &Conf,
+        dir: PathBuf,
+    ) -> Result<Self> {
+        let storage = StorageKind::from_conf(conf)?.open(&dir)?;
         let verbose = false;
         let read_only = false;
         Ok(Self {
             dir,
             verbose,
             read_only,
+            storage,
+            clients: ForgeClients::default(),
         })
     }
-    pub fn user_stars_dir(
+    /// return the client of the given host (github.com when there's
+    /// no name), creating it at first use
+    pub fn forge_client(
         &self,
-        user_id: &UserId,
-    ) -> PathBuf {
-        self.dir.join("stars").join(user_id.to_string())
+        conf: &Conf,
+        host: &Option<String>,
+    ) -> Result<Arc<dyn Forge>> {
+        let mut clients = self
+            .clients
+            .0
+            .lock()
+            .map_err(|_| anyhow!("poisoned forge clients"))?;
+        if let Some(forge) = clients.get(host) {
+            return Ok(forge.clone());
+        }
+        let forge = forge_client(conf, host.as_deref())?;
+        clients.insert(host.clone(), forge.clone());
+        Ok(forge)
     }
     pub fn last_user_obs(
         &self,
         user_id: &UserId,
     ) -> Result<Option<UserObs>> {
-        let user_dir = self.user_stars_dir(user_id);
-        if !user_dir.exists() {
-            return Ok(None);
-        }
-        // TODO: parsing all filenames as dates serves both as filter and
-        // to get the most recent one, but it should be more efficient
-        // to just look in order at the most recent one as given by
-        // the metadata until one is valid
-        fs::read_dir(user_dir)?
-            .filter_map(|e| e.ok())
-            .map(|e| e.path())
-            .filter_map(|path| UserObs::filename_date(&path).map(|date| (path, date)))
-            .max_by_key(|t| t.1)
-            .map(|(path, date)| UserObs::read_file(&path, user_id.clone(), date))
-            .transpose()
+        self.storage.last_user_obs(user_id)
     }
     pub fn count_user_obs(
         &self,
         user_id: &UserId,
     ) -> Result<usize> {
-        let user_dir = self.user_stars_dir(user_id);
-        if !user_dir.exists() {
-            return Ok(0);
-        }
-        // we currently don't check there's no extraneous file in the directory
-        Ok(fs::read_dir(user_dir)?.count())
+        self.storage.count_user_obs(user_id)
     }
     /// read in database the time serie made of
     /// the total numbers of stars of a user per date.
@@ -84,107 +124,550 @@ impl Db {
         &self,
         user_id: &UserId,
     ) -> Result<Vec<DatedObs>> {
-        let user_dir = self.user_stars_dir(user_id);
-        if !user_dir.exists() {
-            bail!("no data for user {}", user_id);
-        }
-        Ok(fs::read_dir(user_dir)?
-            .filter_map(|e| e.ok())
-            .filter_map(|e| {
-                UserObs::filename_date(&e.path())
-                    .and_then(|date| UserObs::read_file(&e.path(), user_id.clone(), date).ok())
-                    .map(|uo| uo.sum())
-            })
-            .collect())
+        self.storage.extract_user(user_id)
     }
     /// fetches and return the (unordered) lines, one per time,
-    /// for the given user query
+    /// with the values of the metric for the given user query
     pub fn extract_user_query(
         &self,
         user_id: &UserId,
         repo_names: Vec<&str>,
+        metric: Metric,
     ) -> Result<Vec<UserResponseLine>> {
-        let user_dir = self.user_stars_dir(user_id);
-        debug!("user_dir: {:#?}", &user_dir);
-        if !user_dir.exists() {
-            bail!("no data for user {}", user_id);
-        }
-        let mut lines = Vec::new();
-        for path in fs::read_dir(user_dir)?
-            .filter_map(|e| e.ok())
-            .map(|e| e.path())
-        {
-            debug!("path: {:#?}", &path);
-            if let Some(time) = UserObs::filename_date(&path) {
-                debug!("reading file {:?}", path);
-                //let uo = UserObs::read_file(&path, user_query.user_id.clone(), time)?;
-                let (counts, sum) = UserObs::read_lines_and_sum(&path, &repo_names)?;
-                lines.push(UserResponseLine { time, sum, counts });
+        self.storage.extract_user_query(user_id, repo_names, metric)
+    }
+    /// return the ids of all users having observations
+    pub fn user_ids(&self) -> Result<Vec<UserId>> {
+        self.storage.user_ids()
+    }
+    /// return the times of all observations of a user, sorted
+    pub fn user_obs_times(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<DateTime<Utc>>> {
+        self.storage.user_obs_times(user_id)
+    }
+    /// return all heartbeats of a user, sorted
+    pub fn heartbeats(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<Heartbeat>> {
+        self.storage.heartbeats(user_id)
+    }
+    /// compare, for each given owner, the last observation stored at
+    /// `from` (or the first one after, for owners followed since) with
+    /// the last one stored at `to`, and return the changes.
+    ///
+    /// No forge is queried.
+    pub fn changes_between(
+        &self,
+        owners: &[UserId],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<RepoChange>> {
+        let mut changes = Vec::new();
+        for user_id in owners {
+            if let Some((old_time, new_time)) = self.period_ends(user_id, from, to)? {
+                self.diff_stored(user_id, old_time, new_time, &mut changes)?;
+            }
+        }
+        detect_transfers(&mut changes);
+        Ok(changes)
+    }
+    /// the times of the two observations of an owner compared
+    /// by `changes_between`, if there are two
+    fn period_ends(
+        &self,
+        user_id: &UserId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        let times = self.user_obs_times(user_id)?;
+        let Some(&new_time) = times.iter().rev().find(|&&time| time <= to) else {
+            return Ok(None);
+        };
+        let old_time = times
+            .iter()
+            .rev()
+            .find(|&&time| time <= from)
+            .or_else(|| times.first());
+        Ok(old_time
+            .filter(|&&time| time < new_time)
+            .map(|&old_time| (old_time, new_time)))
+    }
+    /// return the owners for which one of the observations compared
+    /// by `changes_between` was reconstructed by a backfill, with the
+    /// times of these observations
+    pub fn reconstructed_ends(
+        &self,
+        owners: &[UserId],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<(UserId, Vec<DateTime<Utc>>)>> {
+        let mut reconstructed = Vec::new();
+        for user_id in owners {
+            let Some((old_time, new_time)) = self.period_ends(user_id, from, to)? else {
+                continue;
+            };
+            let mut times = Vec::new();
+            for time in [old_time, new_time] {
+                if self
+                    .storage
+                    .read_user_obs(user_id, time)?
+                    .is_reconstructed()
+                {
+                    times.push(time);
+                }
+            }
+            if !times.is_empty() {
+                reconstructed.push((user_id.clone(), times));
+            }
+        }
+        Ok(reconstructed)
+    }
+    /// compare, for each given owner, the two most recent stored
+    /// observations, and return the changes.
+    ///
+    /// No forge is queried.
+    pub fn last_changes(
+        &self,
+        owners: &[UserId],
+    ) -> Result<Vec<RepoChange>> {
+        let mut changes = Vec::new();
+        for user_id in owners {
+            let times = self.user_obs_times(user_id)?;
+            if let [.., old_time, new_time] = times[..] {
+                self.diff_stored(user_id, old_time, new_time, &mut changes)?;
+            }
+        }
+        detect_transfers(&mut changes);
+        Ok(changes)
+    }
+    /// read two stored observations of an owner and append
+    /// their differences to changes
+    fn diff_stored(
+        &self,
+        user_id: &UserId,
+        old_time: DateTime<Utc>,
+        new_time: DateTime<Utc>,
+        changes: &mut Vec<RepoChange>,
+    ) -> Result<()> {
+        let old_user_obs = self.storage.read_user_obs(user_id, old_time)?;
+        let new_user_obs = self.storage.read_user_obs(user_id, new_time)?;
+        changes.append(&mut new_user_obs.diff_from(&old_user_obs));
+        Ok(())
+    }
+
+    /// return all the follow observations of a user, sorted
+    pub fn follow_obss(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<FollowObs>> {
+        self.storage.follow_obss(user_id)
+    }
+    /// compare, for each given user, the numbers of followers and
+    /// followed users stored at `from` with the last ones stored
+    /// at `to`, and return the changes
+    pub fn follow_changes_between(
+        &self,
+        user_ids: &[UserId],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<FollowChange>> {
+        let mut changes = Vec::new();
+        for user_id in user_ids {
+            let follow_obss = self.storage.follow_obss(user_id)?;
+            let Some(new) = follow_obss.iter().rev().find(|fo| fo.time <= to) else {
+                continue;
+            };
+            let old = follow_obss
+                .iter()
+                .rev()
+                .find(|fo| fo.time <= from)
+                .or_else(|| follow_obss.first());
+            if let Some(change) = old.and_then(|old| follow_change(user_id, old, new)) {
+                changes.push(change);
+            }
+        }
+        Ok(changes)
+    }
+    /// fetch the numbers of followers and followed users of the given
+    /// owners which are users, store them when they changed, and
+    /// return the changes
+    pub async fn update_follows(
+        &self,
+        conf: &Conf,
+        user_ids: &[UserId],
+    ) -> Result<Vec<FollowChange>> {
+        let mut clients: HashMap<Option<String>, Arc<dyn Forge>> = HashMap::new();
+        for user_id in user_ids {
+            if !clients.contains_key(&user_id.host) {
+                clients.insert(
+                    user_id.host.clone(),
+                    self.forge_client(conf, &user_id.host)?,
+                );
+            }
+        }
+        let now = Utc::now().trunc_subsecs(0);
+        let results = stream::iter(user_ids.iter().filter(|u| u.kind == OwnerKind::User))
+            .map(|user_id| {
+                let forge = clients[&user_id.host].clone();
+                async move { (user_id, forge.get_follow_counts(user_id).await) }
+            })
+            .buffer_unordered(MAX_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        let mut changes = Vec::new();
+        for (user_id, result) in results {
+            let follow_counts = match result {
+                Ok(Some(follow_counts)) => follow_counts,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("Error reading the followers of {}: {:?}", user_id, e);
+                    continue;
+                }
+            };
+            let follow_obs = FollowObs::new(now, follow_counts);
+            let last = self.storage.follow_obss(user_id)?.pop();
+            if last
+                .as_ref()
+                .is_some_and(|last| last.counts() == follow_counts)
+            {
+                continue;
+            }
+            if !self.read_only {
+                self.storage.write_follow_obs(user_id, &follow_obs)?;
+            }
+            if let Some(change) = last.and_then(|last| follow_change(user_id, &last, &follow_obs)) {
+                changes.push(change);
+            }
+        }
+        changes.sort_by(|a, b| a.user_id.login.cmp(&b.user_id.login));
+        Ok(changes)
+    }
+    /// return all stargazer events of a repository, sorted
+    pub fn stargazer_events(
+        &self,
+        repo_id: &RepoId,
+    ) -> Result<Vec<StargazerEvent>> {
+        self.storage.stargazer_events(repo_id)
+    }
+    /// fetch the stargazers of a repository, store the changes since
+    /// the last time, and return them
+    pub async fn update_stargazers(
+        &self,
+        forge: &dyn Forge,
+        repo_id: &RepoId,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<StargazerEvent>> {
+        let history = forge.get_star_history(repo_id).await?;
+        let known = current_stargazers(&self.storage.stargazer_events(repo_id)?);
+        let events = stargazer_events(&known, &history, now);
+        if !events.is_empty() && !self.read_only {
+            self.storage.write_stargazer_events(repo_id, &events)?;
+        }
+        Ok(events)
+    }
+    /// return the recorded repositories the given users starred
+    /// between two times, sorted by date
+    pub fn starred_between(
+        &self,
+        user_ids: &[UserId],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<StarredFeedItem>> {
+        let mut items = Vec::new();
+        for user_id in user_ids {
+            items.extend(
+                self.storage
+                    .starred(user_id)?
+                    .into_iter()
+                    .filter(|s| from < s.starred_at && s.starred_at <= to)
+                    .map(|starred| StarredFeedItem {
+                        user_id: user_id.clone(),
+                        starred,
+                    }),
+            );
+        }
+        items.sort_by_key(|item| item.starred.starred_at);
+        Ok(items)
+    }
+    /// fetch the repositories starred, since the last time, by the
+    /// given users whose starred feed is followed, store them, and
+    /// return them.
+    ///
+    /// The first fetch of a user only records the most recent stars,
+    /// as a reference: nothing is returned for this user.
+    pub async fn update_starred(
+        &self,
+        conf: &Conf,
+        user_ids: &[UserId],
+    ) -> Result<Vec<StarredFeedItem>> {
+        let mut items = Vec::new();
+        for user_id in user_ids {
+            if !conf.starred_feeds.contains(&user_id.to_string()) {
+                continue;
+            }
+            let forge = self.forge_client(conf, &user_id.host)?;
+            let since = self.storage.starred(user_id)?.last().map(|s| s.starred_at);
+            let starred = match forge.get_starred_repos(user_id, since).await {
+                Ok(starred) => starred,
+                Err(e) => {
+                    eprintln!("Error reading the stars given by {}: {:?}", user_id, e);
+                    continue;
+                }
+            };
+            if starred.is_empty() {
+                continue;
+            }
+            if !self.read_only {
+                self.storage.write_starred(user_id, &starred)?;
+            }
+            if since.is_some() {
+                items.extend(starred.into_iter().map(|starred| StarredFeedItem {
+                    user_id: user_id.clone(),
+                    starred,
+                }));
             }
         }
-        Ok(lines)
+        items.sort_by_key(|item| item.starred.starred_at);
+        Ok(items)
+    }
+    /// store an observation as is, unless the db is read only
+    pub fn store(
+        &self,
+        user_obs: &UserObs,
+    ) -> Result<()> {
+        if !self.read_only {
+            self.storage.write_user_obs(user_obs, self.verbose)?;
+        }
+        Ok(())
+    }
+    /// store a fresh user observation if it differs from the
+    /// last stored one, appending the differences to changes
+    fn record(
+        &self,
+        user_obs: UserObs,
+        changes: &mut Vec<RepoChange>,
+    ) -> Result<()> {
+        let mut status = CheckStatus::Changed;
+        if let Some(old_user_obs) = self.last_user_obs(&user_obs.user_id)? {
+            let mut diff = user_obs.diff_from(&old_user_obs);
+            changes.append(&mut diff);
+            // other metrics may have changed even when stars didn't
+            if user_obs.delta_from(&old_user_obs).is_empty() {
+                status = CheckStatus::Unchanged;
+            } else if !self.read_only {
+                self.storage.write_user_obs(&user_obs, self.verbose)?;
+            }
+        } else {
+            debug!("{} enters the db", &user_obs.user_id);
+            if !self.read_only {
+                self.storage.write_user_obs(&user_obs, self.verbose)?;
+            }
+        }
+        self.record_heartbeat(&user_obs.user_id, Heartbeat::new(user_obs.time, status))
+    }
+    fn record_heartbeat(
+        &self,
+        user_id: &UserId,
+        heartbeat: Heartbeat,
+    ) -> Result<()> {
+        if !self.read_only {
+            self.storage.write_heartbeat(user_id, &heartbeat)?;
+        }
+        Ok(())
     }
 
+    /// fetch the counts of all followed owners and repositories,
+    /// store them, and return the changes
     pub async fn update(
         &self,
         conf: &Conf,
     ) -> Result<Vec<RepoChange>> {
-        let n = conf.watched_users.len();
-        if n == 0 {
-            eprintln!("No user followed. Use `starry follow some_name` to add one.");
+        let owners = conf.watched_owners();
+        let repo_ids = conf.watched_repo_ids();
+        if owners.is_empty() && repo_ids.is_empty() {
+            eprintln!(
+                "Nothing followed. Use `starry follow some_name` to add a user or a repository."
+            );
             return Ok(vec![]);
         }
+        self.update_some(conf, owners, repo_ids).await
+    }
+    /// fetch the counts of the given owners and repositories,
+    /// store them, and return the changes
+    pub async fn update_some(
+        &self,
+        conf: &Conf,
+        owners: Vec<UserId>,
+        repo_ids: Vec<RepoId>,
+    ) -> Result<Vec<RepoChange>> {
+        // the clients of the hosts of this update
+        let mut clients: HashMap<Option<String>, Arc<dyn Forge>> = HashMap::new();
+        for host in owners
+            .iter()
+            .chain(repo_ids.iter().map(|r| &r.owner))
+            .map(|o| &o.host)
+        {
+            if !clients.contains_key(host) {
+                clients.insert(host.clone(), self.forge_client(conf, host)?);
+            }
+        }
+        // stargazers are fetched only for the tracked repositories
+        // of the owners and repositories of this update
+        let tracked: Vec<RepoId> = conf
+            .tracked_stargazer_repo_ids()
+            .into_iter()
+            .filter(|repo_id| {
+                let owner = repo_id.owner.to_string();
+                owners.iter().any(|o| o.to_string() == owner) || repo_ids.contains(repo_id)
+            })
+            .collect();
+        let n = owners.len();
         let task = Arc::new(Mutex::new(
             Task::new(format!("Query {n} users")).with_total(n),
         ));
 
-        // we use the same date, so that it will look better in extracts
-        let now = Utc::now();
+        // we use the same date, so that it will look better in extracts,
+        // truncated to the precision of the stored times
+        let now = Utc::now().trunc_subsecs(0);
 
-        let results = stream::iter(conf.watched_users.clone())
-            .map(|user| {
-                let github_client = GithubClient::new(conf).unwrap();
+        let results = stream::iter(owners)
+            .map(|user_id| {
+                let forge = clients[&user_id.host].clone();
                 let task = task.clone();
-                let user_id = UserId::new(user);
                 tokio::spawn(async move {
-                    let user_obs = github_client
-                        .get_user_star_counts(user_id.clone(), now)
-                        .await;
+                    let user_obs = forge.get_user_star_counts(user_id.clone(), now).await;
                     task.lock().await.increment();
-                    user_obs.map(|user_obs| (user_id, user_obs))
+                    (user_id, user_obs)
                 })
             })
-            .buffer_unordered(50)
+            .buffer_unordered(MAX_CONCURRENCY)
             .collect::<Vec<_>>()
             .await;
         let mut changes = Vec::new();
         for result in results.into_iter() {
             match result {
-                Ok(Ok((user_id, user_obs))) => {
-                    let user_dir = self.user_stars_dir(&user_id);
-                    if let Some(old_user_obs) = self.last_user_obs(&user_id)? {
-                        let mut diff = user_obs.diff_from(&old_user_obs);
-                        if !diff.is_empty() {
-                            changes.append(&mut diff);
-                            if !self.read_only {
-                                user_obs.write_in_dir(&user_dir, self.verbose)?;
-                            }
-                        }
-                    } else {
-                        debug!("{} enters the db", &user_id);
-                        if !self.read_only {
-                            user_obs.write_in_dir(&user_dir, self.verbose)?;
-                        }
-                    }
+                Ok((_, Ok(user_obs))) => {
+                    self.record(user_obs, &mut changes)?;
+                }
+                Ok((user_id, Err(e))) => {
+                    eprintln!("Error reading user changes: {:?}", e);
+                    self.record_heartbeat(&user_id, Heartbeat::new(now, CheckStatus::Failed))?;
                 }
                 e => {
                     eprintln!("Error reading user changes: {:?}", e);
                 }
             }
         }
+        // repositories followed on their own are fetched together
+        // and stored per owner, like the repositories of followed users
+        for (host, forge) in &clients {
+            let host_repo_ids: Vec<RepoId> = repo_ids
+                .iter()
+                .filter(|repo_id| &repo_id.owner.host == host)
+                .cloned()
+                .collect();
+            if host_repo_ids.is_empty() {
+                continue;
+            }
+            match forge.get_repos_star_counts(&host_repo_ids, now).await {
+                Ok(user_obss) => {
+                    for user_obs in user_obss {
+                        self.record(user_obs, &mut changes)?;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error reading repository changes: {:?}", e);
+                    let mut owners: Vec<&UserId> = host_repo_ids.iter().map(|r| &r.owner).collect();
+                    owners.sort_by(|a, b| a.login.cmp(&b.login));
+                    owners.dedup();
+                    for owner in owners {
+                        self.record_heartbeat(owner, Heartbeat::new(now, CheckStatus::Failed))?;
+                    }
+                }
+            }
+        }
+        for repo_id in &tracked {
+            let forge = &clients[&repo_id.owner.host];
+            if let Err(e) = self.update_stargazers(&**forge, repo_id, now).await {
+                eprintln!("Error reading the stargazers of {}: {:?}", repo_id, e);
+            }
+        }
+        detect_transfers(&mut changes);
+        for forge in clients.values() {
+            if let Err(e) = locate_disappeared(&**forge, &mut changes).await {
+                eprintln!("Error locating disappeared repositories: {:?}", e);
+            }
+        }
         task.lock()
             .await
             .finish(format!("Found {} changes", changes.len()));
+        for forge in clients.values() {
+            if let Some(report) = forge.budget_report() {
+                eprintln!("{} API: {}", forge.host(), report);
+            }
+        }
         Ok(changes)
     }
 }
+
+/// ask a forge where its disappeared repositories went, to
+/// change their disappearance into a transfer when they're found.
+///
+/// The repositories found where they were only left the followed
+/// ones (eg after an unfollow): their changes are dropped
+async fn locate_disappeared(
+    forge: &dyn Forge,
+    changes: &mut Vec<RepoChange>,
+) -> Result<()> {
+    let host = &forge.host().name;
+    let ids: Vec<String> = changes
+        .iter()
+        .filter(|c| c.kind == ChangeKind::Disappeared && &c.repo_id.owner.host == host)
+        .filter_map(|c| c.id.clone())
+        .collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let located = forge.locate_repos(&ids).await?;
+    let mut still_there = Vec::new();
+    for change in changes.iter_mut() {
+        if change.kind != ChangeKind::Disappeared || &change.repo_id.owner.host != host {
+            continue;
+        }
+        let Some(id) = &change.id else {
+            continue;
+        };
+        let Some((repo_id, stars)) = located.get(id) else {
+            continue;
+        };
+        // forges give the canonical case of the names, which
+        // may not be the one the user typed
+        if !repo_id
+            .owner
+            .login
+            .eq_ignore_ascii_case(&change.repo_id.owner.login)
+        {
+            change.kind = ChangeKind::Transferred {
+                from: change.repo_id.clone(),
+            };
+        } else if !repo_id.name.eq_ignore_ascii_case(&change.repo_id.name) {
+            change.kind = ChangeKind::Renamed {
+                old_name: change.repo_id.name.clone(),
+            };
+        } else {
+            still_there.push(id.clone());
+            continue;
+        }
+        change.repo_id = repo_id.clone();
+        change.new_stars = Some(*stars);
+    }
+    changes.retain(|c| {
+        c.kind != ChangeKind::Disappeared
+            || &c.repo_id.owner.host != host
+            || !c.id.as_ref().is_some_and(|id| still_there.contains(id))
+    });
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use {
+    crate::*,
+    anyhow::*,
+    chrono::{
+        DateTime,
+        SecondsFormat,
+        Utc,
+    },
+    std::{
+        ffi::OsStr,
+        fs,
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+};
+
+/// in delta mode, a full observation (a keyframe) is written
+/// when there are already this number of deltas since the last one
+pub const KEYFRAME_INTERVAL: usize = 20;
+
+/// a line in a delta file: the new counts of a repo, or the
+/// removal of the repo (written with empty counts)
+#[derive(Debug, Clone, PartialEq)]
+pub enum RepoDelta {
+    Set(RepoObs),
+    Removed(String),
+}
+
+/// a file of the CSV storage, either a full observation
+/// (`date.csv`) or a delta from the previous one (`date.delta.csv`)
+#[derive(Debug, Clone)]
+pub struct ObsFile {
+    pub path: PathBuf,
+    pub time: DateTime<Utc>,
+    pub delta: bool,
+}
+
+impl ObsFile {
+    pub fn from_path(path: PathBuf) -> Option<Self> {
+        if let Some(time) = UserObs::filename_date(&path) {
+            return Some(Self {
+                path,
+                time,
+                delta: false,
+            });
+        }
+        UserObs::delta_filename_date(&path).map(|time| Self {
+            path,
+            time,
+            delta: true,
+        })
+    }
+    /// list the observation files of a directory, sorted by date
+    pub fn list(user_dir: &Path) -> Result<Vec<Self>> {
+        let mut files: Vec<Self> = fs::read_dir(user_dir)?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| Self::from_path(e.path()))
+            .collect();
+        files.sort_by_key(|f| f.time);
+        Ok(files)
+    }
+    /// replay the file on the state reconstructed from
+    /// the previous files
+    pub fn apply_to(
+        &self,
+        user_obs: &mut UserObs,
+    ) -> Result<()> {
+        if self.delta {
+            let deltas = UserObs::read_delta_file(&self.path)?;
+            user_obs.apply_delta(&deltas);
+        } else {
+            user_obs.counts =
+                UserObs::read_file(&self.path, user_obs.user_id.clone(), self.time)?.counts;
+        }
+        user_obs.time = self.time;
+        Ok(())
+    }
+}
+
+impl UserObs {
+    /// the name of the file of a delta at this time
+    pub fn delta_filename(time: DateTime<Utc>) -> String {
+        format!(
+            "{}.delta.csv",
+            time.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )
+    }
+    /// if the file has a name matching the delta filename
+    /// format (date.delta.csv), then returns this date
+    pub fn delta_filename_date(path: &Path) -> Option<DateTime<Utc>> {
+        path.file_name()
+            .and_then(OsStr::to_str)
+            .and_then(|name| name.strip_suffix(".delta.csv"))
+            .and_then(|stem| DateTime::parse_from_rfc3339(stem).ok())
+            .map(DateTime::from)
+    }
+    /// compute what must be applied to the old observation
+    /// to get this one
+    pub fn delta_from(
+        &self,
+        old_uo: &Self,
+    ) -> Vec<RepoDelta> {
+        let mut deltas = Vec::new();
+        for repo_obs in &self.counts {
+            if old_uo.repo(&repo_obs.repo_name) != Some(repo_obs) {
+                deltas.push(RepoDelta::Set(repo_obs.clone()));
+            }
+        }
+        for old_repo_obs in &old_uo.counts {
+            if self.repo(&old_repo_obs.repo_name).is_none() {
+                deltas.push(RepoDelta::Removed(old_repo_obs.repo_name.clone()));
+            }
+        }
+        deltas
+    }
+    pub fn apply_delta(
+        &mut self,
+        deltas: &[RepoDelta],
+    ) {
+        for delta in deltas {
+            match delta {
+                RepoDelta::Set(repo_obs) => {
+                    let existing = self
+                        .counts
+                        .iter_mut()
+                        .find(|ro| ro.repo_name == repo_obs.repo_name);
+                    match existing {
+                        Some(existing) => {
+                            *existing = repo_obs.clone();
+                        }
+                        None => {
+                            self.counts.push(repo_obs.clone());
+                        }
+                    }
+                }
+                RepoDelta::Removed(repo_name) => {
+                    self.counts.retain(|ro| &ro.repo_name != repo_name);
+                }
+            }
+        }
+    }
+    pub fn write_delta_in_dir(
+        &self,
+        deltas: &[RepoDelta],
+        user_dir: &Path,
+        verbose: bool,
+    ) -> Result<()> {
+        fs::create_dir_all(user_dir)?;
+        let file_path = user_dir.join(Self::delta_filename(self.time));
+        let columns = CsvColumns::for_obs(deltas.iter().filter_map(|delta| match delta {
+            RepoDelta::Set(repo_obs) => Some(repo_obs),
+            RepoDelta::Removed(_) => None,
+        }));
+        let mut w = csv::Writer::from_path(&file_path)?;
+        w.write_record(columns.header())?;
+        for delta in deltas {
+            match delta {
+                RepoDelta::Set(repo_obs) => {
+                    w.write_record(columns.record(repo_obs))?;
+                }
+                RepoDelta::Removed(repo_name) => {
+                    let mut record = vec![repo_name.as_str()];
+                    record.resize(columns.count(), "");
+                    w.write_record(record)?;
+                }
+            }
+        }
+        w.flush()?;
+        if verbose {
+            println!("wrote file {:?}", file_path);
+        }
+        Ok(())
+    }
+    pub fn read_delta_file(file_path: &Path) -> Result<Vec<RepoDelta>> {
+        let mut r = csv::Reader::from_path(file_path)?;
+        let headers = r.headers()?.clone();
+        let stars_idx = headers
+            .iter()
+            .position(|h| h == "stars")
+            .ok_or_else(|| anyhow!("no stars column"))?;
+        let mut deltas = Vec::new();
+        for record in r.records() {
+            let record = record?;
+            if record.get(stars_idx).is_none_or(str::is_empty) {
+                deltas.push(RepoDelta::Removed(record[0].to_string()));
+            } else {
+                deltas.push(RepoDelta::Set(record.deserialize(Some(&headers))?));
+            }
+        }
+        Ok(deltas)
+    }
+}
@@ -7,6 +7,7 @@ use {
         Utc,
     },
     cli_log::*,
+    serde_json::Value,
     std::{
         collections::HashMap,
         io::Write,
@@ -18,6 +19,9 @@ pub struct ExtractLine {
     pub time: DateTime<Utc>,
     // this vec is the same len than the extract's names
     pub counts: Vec<Option<usize>>,
+    /// whether some value comes from an observation synthesized
+    /// by a backfill instead of really observed
+    pub reconstructed: bool,
 }
 
 #[derive(Debug)]
@@ -34,42 +38,59 @@ pub(crate) struct Col {
 }
 
 impl Extract {
-    pub fn write_csv<W: Write>(
-        &self,
-        w: &mut W,
-    ) -> Result<()> {
-        write!(w, "time")?;
-        for name in &self.names {
-            write!(w, ",{}", name)?;
+    /// whether some line comes from a reconstructed observation
+    pub fn has_reconstructed(&self) -> bool {
+        self.lines.iter().any(|line| line.reconstructed)
+    }
+    /// the extract as a table, with a time column then one column per name,
+    /// and a `reconstructed` column when some lines come from a backfill
+    pub fn table(&self) -> Table {
+        let reconstructed = self.has_reconstructed();
+        let mut columns = vec!["time".to_string()];
+        columns.extend(self.names.iter().cloned());
+        if reconstructed {
+            columns.push("reconstructed".to_string());
         }
-        writeln!(w)?;
+        let mut table = Table::new(columns);
         for line in &self.lines {
-            write!(
-                w,
-                "{}",
-                line.time.to_rfc3339_opts(SecondsFormat::Secs, true)
-            )?;
-            for count in &line.counts {
-                if let Some(count) = count {
-                    write!(w, ",{}", count)?;
-                } else {
-                    write!(w, ",")?;
-                }
+            let mut row = vec![line.time.to_rfc3339_opts(SecondsFormat::Secs, true).into()];
+            row.extend(line.counts.iter().map(|&count| Value::from(count)));
+            if reconstructed {
+                row.push(line.reconstructed.into());
             }
-            writeln!(w)?;
+            table.add_row(row);
         }
-        w.flush()?;
-        Ok(())
+        table
+    }
+    pub fn write<W: Write>(
+        &self,
+        format: OutputFormat,
+        w: &mut W,
+    ) -> Result<()> {
+        self.table().write(format, w)
     }
+    /// read the time series of the given metric for the given users and repos.
+    ///
+    /// When `heartbeats` is true, there's a line at every successful
+    /// check of a user, with the values carried forward from the last
+    /// observation, instead of only when the values changed.
     pub fn read(
         db: &Db,
         names: Vec<String>,
+        metric: Metric,
+        heartbeats: bool,
     ) -> Result<Self> {
-        // we first compile the user request in several queries (one per user)
+        // we first compile the user request in several queries (one per user),
+        // the follower series (`login:followers`) being read apart
         let mut queries: Vec<UserQuery> = Vec::new();
+        let mut follow_cols: Vec<(usize, UserId, FollowField)> = Vec::new();
         for (idx, name) in names.iter().enumerate() {
+            if let Some((login, field)) = name.split_once(':') {
+                follow_cols.push((idx, UserId::parse(login), FollowField::parse(field)?));
+                continue;
+            }
             let mut tokens = name.split('/');
-            let user_id = UserId::new(tokens.next().unwrap()); // SAFETY: first split element is never None
+            let user_id = UserId::parse(tokens.next().unwrap()); // SAFETY: first split element is never None
             let query_idx = queries
                 .iter()
                 .position(|q| q.user_id == user_id)
@@ -104,7 +125,10 @@ impl Extract {
         let mut results: HashMap<DateTime<Utc>, ExtractLine> = HashMap::new();
         for query in queries {
             let repo_names = query.repos.iter().map(|col| col.name.as_str()).collect();
-            let response_lines = db.extract_user_query(&query.user_id, repo_names)?;
+            let mut response_lines = db.extract_user_query(&query.user_id, repo_names, metric)?;
+            if heartbeats {
+                response_lines = carry_forward(response_lines, &db.heartbeats(&query.user_id)?);
+            }
             debug!("response_lines: {:#?}", &response_lines);
             for response_line in response_lines {
                 let extract_line =
@@ -113,13 +137,44 @@ impl Extract {
                         .or_insert_with(|| ExtractLine {
                             time: response_line.time,
                             counts: vec![None; names.len()],
+                            reconstructed: false,
                         });
                 if let Some(col) = query.sum.as_ref() {
-                    extract_line.counts[col.idx] = Some(response_line.sum);
+                    extract_line.counts[col.idx] = response_line.sum;
                 }
                 for (idx, col) in query.repos.iter().enumerate() {
                     extract_line.counts[col.idx] = response_line.counts[idx];
                 }
+                extract_line.reconstructed |= response_line.reconstructed;
+            }
+        }
+        for (idx, user_id, field) in follow_cols {
+            let mut response_lines: Vec<UserResponseLine> = db
+                .follow_obss(&user_id)?
+                .into_iter()
+                .map(|follow_obs| UserResponseLine {
+                    time: follow_obs.time,
+                    sum: Some(follow_obs.get(field)),
+                    counts: Vec::new(),
+                    reconstructed: false,
+                })
+                .collect();
+            if response_lines.is_empty() {
+                bail!("no follower data for {}", user_id);
+            }
+            if heartbeats {
+                response_lines = carry_forward(response_lines, &db.heartbeats(&user_id)?);
+            }
+            for response_line in response_lines {
+                let extract_line =
+                    results
+                        .entry(response_line.time)
+                        .or_insert_with(|| ExtractLine {
+                            time: response_line.time,
+                            counts: vec![None; names.len()],
+                            reconstructed: false,
+                        });
+                extract_line.counts[idx] = response_line.sum;
             }
         }
         // we sort the lines
@@ -0,0 +1,140 @@
+use {
+    crate::*,
+    anyhow::{
+        bail,
+        Result,
+    },
+    chrono::{
+        DateTime,
+        Utc,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    std::fmt,
+};
+
+/// the numbers of followers and followed users of a user at some time
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FollowObs {
+    pub time: DateTime<Utc>,
+    pub followers: usize,
+    pub following: usize,
+}
+
+/// a change in the numbers of followers or followed users of a user
+#[derive(Debug, Clone)]
+pub struct FollowChange {
+    pub user_id: UserId,
+    pub old: FollowCounts,
+    pub new: FollowCounts,
+}
+
+/// one of the two series of a user's audience, which can be
+/// extracted with `login:followers` or `login:following`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FollowField {
+    Followers,
+    Following,
+}
+
+impl FollowObs {
+    pub fn new(
+        time: DateTime<Utc>,
+        counts: FollowCounts,
+    ) -> Self {
+        Self {
+            time,
+            followers: counts.followers,
+            following: counts.following,
+        }
+    }
+    pub fn counts(&self) -> FollowCounts {
+        FollowCounts {
+            followers: self.followers,
+            following: self.following,
+        }
+    }
+    pub fn get(
+        &self,
+        field: FollowField,
+    ) -> usize {
+        match field {
+            FollowField::Followers => self.followers,
+            FollowField::Following => self.following,
+        }
+    }
+}
+
+impl FollowField {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "followers" => Ok(Self::Followers),
+            "following" => Ok(Self::Following),
+            _ => bail!(
+                "Unknown series {:?}: expected 'followers' or 'following'",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for FollowChange {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{}", self.user_id)?;
+        let fields = [
+            ("followers", self.old.followers, self.new.followers),
+            ("following", self.old.following, self.new.following),
+        ];
+        let mut first = true;
+        for (name, old, new) in fields {
+            if old == new {
+                continue;
+            }
+            let verb = if old < new { "rised" } else { "dropped" };
+            let sep = if first { "" } else { "," };
+            write!(f, "{} {} {} from {} to {}", sep, name, verb, old, new)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// the change between two observations, if there's one
+pub fn follow_change(
+    user_id: &UserId,
+    old: &FollowObs,
+    new: &FollowObs,
+) -> Option<FollowChange> {
+    (old.counts() != new.counts()).then(|| FollowChange {
+        user_id: user_id.clone(),
+        old: old.counts(),
+        new: new.counts(),
+    })
+}
+
+/// the table of follow changes, for the structured outputs
+pub fn follow_changes_table(changes: &[FollowChange]) -> Table {
+    let columns = [
+        "user",
+        "old_followers",
+        "new_followers",
+        "old_following",
+        "new_following",
+    ];
+    let mut table = Table::new(columns.iter().map(|c| c.to_string()).collect());
+    for change in changes {
+        table.add_row(vec![
+            change.user_id.to_string().into(),
+            change.old.followers.into(),
+            change.new.followers.into(),
+            change.old.following.into(),
+            change.new.following.into(),
+        ]);
+    }
+    table
+}
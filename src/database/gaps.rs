@@ -0,0 +1,79 @@
+use {
+    crate::*,
+    anyhow::*,
+    chrono::{
+        DateTime,
+        Duration,
+        SecondsFormat,
+        Utc,
+    },
+    std::io::Write,
+};
+
+/// a period during which a user wasn't checked
+#[derive(Debug)]
+pub struct Gap {
+    pub name: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct Gaps {
+    pub gaps: Vec<Gap>,
+}
+
+impl Gaps {
+    /// find, for the given users, the periods longer than `min_duration`
+    /// without successful check. A user is known to have been checked
+    /// when there's an observation or a successful heartbeat.
+    /// The period since the last check is included.
+    pub fn read(
+        db: &Db,
+        user_ids: &[UserId],
+        min_duration: Duration,
+    ) -> Result<Self> {
+        let now = Utc::now();
+        let mut gaps = Vec::new();
+        for user_id in user_ids {
+            let mut times = db.user_obs_times(user_id)?;
+            times.extend(
+                db.heartbeats(user_id)?
+                    .into_iter()
+                    .filter(|h| h.is_ok())
+                    .map(|h| h.time),
+            );
+            times.sort();
+            times.dedup();
+            times.push(now);
+            for pair in times.windows(2) {
+                if pair[1] - pair[0] > min_duration {
+                    gaps.push(Gap {
+                        name: user_id.to_string(),
+                        from: pair[0],
+                        to: pair[1],
+                    });
+                }
+            }
+        }
+        Ok(Self { gaps })
+    }
+    pub fn write_csv<W: Write>(
+        &self,
+        w: &mut W,
+    ) -> Result<()> {
+        writeln!(w, "name,from,to,hours")?;
+        for gap in &self.gaps {
+            writeln!(
+                w,
+                "{},{},{},{}",
+                gap.name,
+                gap.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+                gap.to.to_rfc3339_opts(SecondsFormat::Secs, true),
+                (gap.to - gap.from).num_hours(),
+            )?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
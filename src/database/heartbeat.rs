@@ -0,0 +1,43 @@
+use {
+    chrono::{
+        DateTime,
+        Utc,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+};
+
+/// the outcome of checking a user during an update
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    /// stars changed, a new observation was stored
+    Changed,
+    /// stars were checked but nothing changed
+    Unchanged,
+    /// the user couldn't be checked
+    Failed,
+}
+
+/// the record of a user being checked, whether
+/// an observation was stored or not
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Heartbeat {
+    pub time: DateTime<Utc>,
+    pub status: CheckStatus,
+}
+
+impl Heartbeat {
+    pub fn new(
+        time: DateTime<Utc>,
+        status: CheckStatus,
+    ) -> Self {
+        Self { time, status }
+    }
+    /// tell whether the stars were really checked
+    pub fn is_ok(&self) -> bool {
+        self.status != CheckStatus::Failed
+    }
+}
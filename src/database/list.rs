@@ -11,62 +11,82 @@ use {
 #[derive(Debug, Serialize)]
 pub struct ListLine {
     pub name: String,
-    pub stars: usize,
+    /// the value of the list's metric
+    pub count: usize,
 }
 
 #[derive(Debug, Serialize)]
 pub struct List {
+    pub metric: Metric,
     pub lines: Vec<ListLine>,
 }
 
 impl List {
     /// find the users with at least 2 user observations, return them
-    /// with the last total count of stars
+    /// with the last total count of the metric
     pub fn users(
         db: &Db,
         conf: &Conf,
         drawable: bool,
+        metric: Metric,
     ) -> Result<Self> {
         let mut lines = Vec::new();
         for name in &conf.watched_users {
-            let user_id = UserId::new(name);
+            let user_id = UserId::parse(name);
             if drawable && db.count_user_obs(&user_id)? < 2 {
                 continue;
             }
             if let Some(uo) = db.last_user_obs(&user_id)? {
-                let stars = uo.sum().stars;
-                lines.push(ListLine {
-                    name: name.to_string(),
-                    stars,
-                });
+                if let Some(count) = uo.sum_metric(metric) {
+                    lines.push(ListLine {
+                        name: name.to_string(),
+                        count,
+                    });
+                }
             }
         }
-        Ok(Self { lines })
+        Ok(Self { metric, lines })
     }
-    pub fn write_csv<W: Write>(
+    /// list the repos of a user observation having a non
+    /// zero value for the metric
+    pub fn repos(
+        mut uo: UserObs,
+        metric: Metric,
+    ) -> Self {
+        let lines = uo
+            .counts
+            .drain(..)
+            .filter_map(|c| {
+                c.get(metric)
+                    .filter(|&count| count > 0)
+                    .map(|count| ListLine {
+                        name: c.repo_name,
+                        count,
+                    })
+            })
+            .collect();
+        Self { metric, lines }
+    }
+    /// the list as a table, with a name column and a column
+    /// named after the metric
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(vec!["name".to_string(), self.metric.to_string()]);
+        for line in &self.lines {
+            table.add_row(vec![line.name.clone().into(), line.count.into()]);
+        }
+        table
+    }
+    pub fn write<W: Write>(
         &self,
+        format: OutputFormat,
         w: &mut W,
     ) -> Result<()> {
-        writeln!(w, "name,stars")?;
-        for line in &self.lines {
-            writeln!(w, "{},{}", line.name, line.stars)?;
-        }
-        w.flush()?;
-        Ok(())
+        self.table().write(format, w)
     }
 }
 
 impl From<UserObs> for List {
-    fn from(mut uo: UserObs) -> Self {
-        let lines = uo
-            .counts
-            .drain(..)
-            .filter(|c| c.stars > 0)
-            .map(|c| ListLine {
-                name: c.repo_name,
-                stars: c.stars,
-            })
-            .collect();
-        Self { lines }
+    fn from(uo: UserObs) -> Self {
+        Self::repos(uo, Metric::Stars)
     }
 }
@@ -0,0 +1,90 @@
+use {
+    anyhow::*,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    std::{
+        fmt,
+        str::FromStr,
+    },
+};
+
+/// the key, in conf, of the list of the metrics to fetch in
+/// addition to stars (eg `starry set metrics forks,issues`)
+pub static METRICS_KEY: &str = "metrics";
+
+/// a count observed on a repository
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    #[default]
+    Stars,
+    Forks,
+    Watchers,
+    /// open issues
+    Issues,
+    /// open pull requests
+    Prs,
+    /// downloads of the assets of the recent releases
+    Downloads,
+}
+
+impl Metric {
+    /// all metrics, in the order of the CSV columns
+    pub const ALL: [Self; 6] = [
+        Self::Stars,
+        Self::Forks,
+        Self::Watchers,
+        Self::Issues,
+        Self::Prs,
+        Self::Downloads,
+    ];
+    /// the name of the metric, used as CSV column and SQL column
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Stars => "stars",
+            Self::Forks => "forks",
+            Self::Watchers => "watchers",
+            Self::Issues => "issues",
+            Self::Prs => "prs",
+            Self::Downloads => "downloads",
+        }
+    }
+    /// parse a comma separated list of metrics, eg "forks,issues"
+    pub fn parse_list(s: &str) -> Result<Vec<Self>> {
+        let mut metrics = Vec::new();
+        for token in s.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let metric: Self = token.parse()?;
+            if !metrics.contains(&metric) {
+                metrics.push(metric);
+            }
+        }
+        Ok(metrics)
+    }
+}
+
+impl FromStr for Metric {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .iter()
+            .find(|m| m.name() == s.to_lowercase())
+            .copied()
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown metric: {:?} (expected one of stars, forks, watchers, issues, prs, downloads)",
+                    s
+                )
+            })
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
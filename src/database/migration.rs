@@ -0,0 +1,198 @@
+use {
+    crate::*,
+    anyhow::Result,
+};
+
+/// the result of a migration between two storages
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub users: usize,
+    /// number of observations read from the source
+    pub read: usize,
+    /// number of observations written and verified in the target
+    pub migrated: usize,
+    /// descriptions of the observations which couldn't be read,
+    /// written or verified
+    pub problems: Vec<String>,
+}
+
+/// copy all observations (and heartbeats, follow observations, stargazer
+/// events and starred repositories) from a storage to another one.
+///
+/// Each written observation is read back from the target and
+/// compared with the source (number of repositories and sum of
+/// stars). Heartbeats, follow observations, stargazer events and
+/// starred repositories already in the target aren't copied again,
+/// so that a migration can be run again after a partial failure.
+/// When `write` is false, the source is only read, which checks
+/// it's fully readable.
+pub fn migrate(
+    from: &dyn Storage,
+    to: &dyn Storage,
+    write: bool,
+    verbose: bool,
+) -> Result<MigrationReport> {
+    let mut report = MigrationReport::default();
+    let user_ids = from.user_ids()?;
+    let task_label = if write { "Migrate" } else { "Read" };
+    let mut task =
+        Task::new(format!("{task_label} {} users", user_ids.len())).with_total(user_ids.len());
+    for user_id in user_ids {
+        report.users += 1;
+        for time in from.user_obs_times(&user_id)? {
+            let user_obs = match from.read_user_obs(&user_id, time) {
+                Ok(user_obs) => user_obs,
+                Err(e) => {
+                    report.problems.push(format!(
+                        "unreadable observation of {user_id} at {time}: {e:#}"
+                    ));
+                    continue;
+                }
+            };
+            report.read += 1;
+            if !write {
+                continue;
+            }
+            if let Err(e) = to.write_user_obs(&user_obs, verbose) {
+                report.problems.push(format!(
+                    "can't write observation of {user_id} at {time}: {e:#}"
+                ));
+                continue;
+            }
+            match to.read_user_obs(&user_id, time) {
+                Ok(written) => {
+                    if written.counts.len() == user_obs.counts.len()
+                        && written.sum().stars == user_obs.sum().stars
+                    {
+                        report.migrated += 1;
+                    } else if written.counts.len() != user_obs.counts.len() {
+                        report.problems.push(format!(
+                            "observation of {user_id} at {time}: {} rows read back instead of {}",
+                            written.counts.len(),
+                            user_obs.counts.len(),
+                        ));
+                    } else {
+                        report.problems.push(format!(
+                            "observation of {user_id} at {time}: sum of stars read back is {} instead of {}",
+                            written.sum().stars,
+                            user_obs.sum().stars,
+                        ));
+                    }
+                }
+                Err(e) => {
+                    report.problems.push(format!(
+                        "can't read back observation of {user_id} at {time}: {e:#}"
+                    ));
+                }
+            }
+        }
+        // the entries already in the target (eg after a partially failed
+        // migration) aren't written again, as the CSV storage appends them
+        let copied = from.heartbeats(&user_id).and_then(|heartbeats| {
+            if write {
+                let existing = to.heartbeats(&user_id)?;
+                for heartbeat in &heartbeats {
+                    if !existing.iter().any(|h| h.time == heartbeat.time) {
+                        to.write_heartbeat(&user_id, heartbeat)?;
+                    }
+                }
+            }
+            Ok(())
+        });
+        if let Err(e) = copied {
+            report
+                .problems
+                .push(format!("can't copy the heartbeats of {user_id}: {e:#}"));
+        }
+        match from.stargazer_repo_names(&user_id) {
+            Ok(names) => {
+                for name in names {
+                    let repo_id = RepoId::new(user_id.clone(), name);
+                    let copied = from.stargazer_events(&repo_id).and_then(|events| {
+                        if write {
+                            let existing = to.stargazer_events(&repo_id)?;
+                            let events: Vec<StargazerEvent> = events
+                                .into_iter()
+                                .filter(|event| {
+                                    !existing
+                                        .iter()
+                                        .any(|e| e.time == event.time && e.login == event.login)
+                                })
+                                .collect();
+                            if !events.is_empty() {
+                                to.write_stargazer_events(&repo_id, &events)?;
+                            }
+                        }
+                        Ok(())
+                    });
+                    if let Err(e) = copied {
+                        report
+                            .problems
+                            .push(format!("can't copy stargazers of {repo_id}: {e:#}"));
+                    }
+                }
+            }
+            Err(e) => {
+                report
+                    .problems
+                    .push(format!("unreadable stargazers of {user_id}: {e:#}"));
+            }
+        }
+        let copied = from.follow_obss(&user_id).and_then(|follow_obss| {
+            if write {
+                let existing = to.follow_obss(&user_id)?;
+                for follow_obs in &follow_obss {
+                    if !existing.iter().any(|f| f.time == follow_obs.time) {
+                        to.write_follow_obs(&user_id, follow_obs)?;
+                    }
+                }
+            }
+            Ok(())
+        });
+        if let Err(e) = copied {
+            report
+                .problems
+                .push(format!("can't copy the followers of {user_id}: {e:#}"));
+        }
+        let copied = from.starred(&user_id).and_then(|starred| {
+            if write {
+                let existing = to.starred(&user_id)?;
+                let starred: Vec<StarredRepo> = starred
+                    .into_iter()
+                    .filter(|s| {
+                        !existing
+                            .iter()
+                            .any(|e| e.starred_at == s.starred_at && e.repo == s.repo)
+                    })
+                    .collect();
+                if !starred.is_empty() {
+                    to.write_starred(&user_id, &starred)?;
+                }
+            }
+            Ok(())
+        });
+        if let Err(e) = copied {
+            report.problems.push(format!(
+                "can't copy the starred repositories of {user_id}: {e:#}"
+            ));
+        }
+        task.increment();
+    }
+    task.finish(format!("{} observations read", report.read));
+    Ok(report)
+}
+
+impl MigrationReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+    pub fn print(&self) {
+        println!(
+            "{} observations of {} users read, {} migrated",
+            self.read, self.users, self.migrated,
+        );
+        for problem in &self.problems {
+            eprintln!("{}", problem);
+        }
+    }
+}
@@ -1,15 +1,39 @@
+mod backfill;
 mod change;
+mod csv_storage;
 mod db;
+mod delta;
 mod extract;
+mod follow_obs;
+mod gaps;
+mod heartbeat;
 mod list;
+mod metric;
+mod migration;
+mod output;
+mod sqlite_storage;
+mod stargazer;
+mod storage;
 mod user_obs;
 mod user_query;
 
 pub use {
+    backfill::*,
     change::*,
+    csv_storage::*,
     db::*,
+    delta::*,
     extract::*,
+    follow_obs::*,
+    gaps::*,
+    heartbeat::*,
     list::*,
+    metric::*,
+    migration::*,
+    output::*,
+    sqlite_storage::*,
+    stargazer::*,
+    storage::*,
     user_obs::*,
     user_query::*,
 };
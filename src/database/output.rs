@@ -0,0 +1,154 @@
+use {
+    anyhow::*,
+    serde_json::{
+        Map,
+        Value,
+    },
+    std::{
+        fmt,
+        io::Write,
+        str::FromStr,
+    },
+};
+
+/// how lists of results (changes, time series, etc.) are written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Csv,
+    Json,
+    /// one JSON object per line
+    Ndjson,
+    Markdown,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_ref() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => bail!(
+                "Unknown format: {:?} (expected 'csv', 'json', 'ndjson' or 'markdown')",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            Self::Csv => write!(f, "csv"),
+            Self::Json => write!(f, "json"),
+            Self::Ndjson => write!(f, "ndjson"),
+            Self::Markdown => write!(f, "markdown"),
+        }
+    }
+}
+
+/// named columns of values, which can be written in any output format.
+///
+/// In JSON, each row is an object whose keys are the column names.
+#[derive(Debug, Default)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<String>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+    pub fn add_row(
+        &mut self,
+        row: Vec<Value>,
+    ) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+    fn objects(&self) -> impl Iterator<Item = Map<String, Value>> + '_ {
+        self.rows.iter().map(move |row| {
+            self.columns
+                .iter()
+                .cloned()
+                .zip(row.iter().cloned())
+                .collect()
+        })
+    }
+    pub fn write<W: Write>(
+        &self,
+        format: OutputFormat,
+        w: &mut W,
+    ) -> Result<()> {
+        match format {
+            OutputFormat::Csv => {
+                let mut csv = csv::Writer::from_writer(&mut *w);
+                csv.write_record(&self.columns)?;
+                for row in &self.rows {
+                    csv.write_record(row.iter().map(cell))?;
+                }
+                csv.flush()?;
+            }
+            OutputFormat::Json => {
+                let objects: Vec<Map<String, Value>> = self.objects().collect();
+                serde_json::to_writer_pretty(&mut *w, &objects)?;
+                writeln!(w)?;
+            }
+            OutputFormat::Ndjson => {
+                for object in self.objects() {
+                    serde_json::to_writer(&mut *w, &object)?;
+                    writeln!(w)?;
+                }
+            }
+            OutputFormat::Markdown => {
+                writeln!(w, "|{}|", self.columns.join("|"))?;
+                writeln!(w, "|{}", "-|".repeat(self.columns.len()))?;
+                for row in &self.rows {
+                    let cells: Vec<String> = row
+                        .iter()
+                        .map(|value| cell(value).replace('|', "\\|"))
+                        .collect();
+                    writeln!(w, "|{}|", cells.join("|"))?;
+                }
+            }
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
+
+/// write named tables as the arrays of a JSON object
+/// whose keys are their names
+pub fn write_json_sections<W: Write>(
+    sections: &[(&str, Table)],
+    w: &mut W,
+) -> Result<()> {
+    let object: Map<String, Value> = sections
+        .iter()
+        .map(|(name, table)| {
+            let objects = table.objects().map(Value::Object).collect();
+            (name.to_string(), Value::Array(objects))
+        })
+        .collect();
+    serde_json::to_writer_pretty(&mut *w, &object)?;
+    writeln!(w)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// the textual content of a cell, empty for missing values
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,541 @@
+use {
+    crate::*,
+    anyhow::{
+        anyhow,
+        bail,
+        Result,
+    },
+    chrono::{
+        DateTime,
+        TimeZone,
+        Utc,
+    },
+    rusqlite::{
+        params,
+        Connection,
+        OptionalExtension,
+    },
+    std::{
+        collections::HashMap,
+        fs,
+        path::Path,
+    },
+};
+
+static SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS user_obs (
+    owner TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    PRIMARY KEY (owner, time)
+);
+CREATE TABLE IF NOT EXISTS repo_obs (
+    owner TEXT NOT NULL,
+    repo TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    stars INTEGER NOT NULL,
+    PRIMARY KEY (owner, repo, time)
+);
+CREATE INDEX IF NOT EXISTS repo_obs_owner_time ON repo_obs (owner, time);
+CREATE TABLE IF NOT EXISTS heartbeat (
+    owner TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    PRIMARY KEY (owner, time)
+);
+CREATE TABLE IF NOT EXISTS follow_obs (
+    owner TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    followers INTEGER NOT NULL,
+    following INTEGER NOT NULL,
+    PRIMARY KEY (owner, time)
+);
+CREATE TABLE IF NOT EXISTS stargazer_event (
+    owner TEXT NOT NULL,
+    repo TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    login TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    PRIMARY KEY (owner, repo, time, login)
+);
+CREATE TABLE IF NOT EXISTS starred (
+    owner TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    repo TEXT NOT NULL,
+    stars INTEGER NOT NULL,
+    PRIMARY KEY (owner, time, repo)
+);
+"#;
+
+/// a storage in a single SQLite file, with one row per
+/// (owner, repo, time). Times are stored as unix timestamps
+/// in seconds, like the precision of the CSV storage.
+///
+/// There's a nullable column in `repo_obs` for every metric
+/// other than stars, and for the id, archived and reconstructed states.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    con: Connection,
+}
+
+/// the comma separated columns of all metrics
+fn metric_columns() -> String {
+    Metric::ALL
+        .iter()
+        .map(|m| m.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn to_time(timestamp: i64) -> Result<DateTime<Utc>> {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .ok_or_else(|| anyhow!("invalid timestamp in database: {}", timestamp))
+}
+
+impl SqliteStorage {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        fs::create_dir_all(data_dir)?;
+        let con = Connection::open(data_dir.join("stars.sqlite"))?;
+        con.execute_batch(SCHEMA)?;
+        let storage = Self { con };
+        storage.add_missing_columns()?;
+        Ok(storage)
+    }
+    /// add the columns missing in the repo_obs table
+    /// (which may have been created by an older version)
+    fn add_missing_columns(&self) -> Result<()> {
+        let mut stmt = self
+            .con
+            .prepare("SELECT name FROM pragma_table_info('repo_obs')")?;
+        let columns = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        let mut expected = vec![
+            ("id", "TEXT"),
+            ("archived", "INTEGER"),
+            ("reconstructed", "INTEGER"),
+        ];
+        expected.extend(Metric::ALL.iter().map(|m| (m.name(), "INTEGER")));
+        for (name, sql_type) in expected {
+            if !columns.iter().any(|c| c == name) {
+                self.con.execute_batch(&format!(
+                    "ALTER TABLE repo_obs ADD COLUMN {} {}",
+                    name, sql_type,
+                ))?;
+            }
+        }
+        Ok(())
+    }
+    fn has_user(
+        &self,
+        user_id: &UserId,
+    ) -> Result<bool> {
+        Ok(self.count_user_obs(user_id)? > 0)
+    }
+    fn read_user_obs_at(
+        &self,
+        user_id: &UserId,
+        timestamp: i64,
+    ) -> Result<UserObs> {
+        let mut stmt = self.con.prepare_cached(&format!(
+            "SELECT repo, id, archived, reconstructed, {} FROM repo_obs WHERE owner=?1 AND time=?2",
+            metric_columns(),
+        ))?;
+        let counts = stmt
+            .query_map(params![user_id.to_string(), timestamp], |row| {
+                let mut repo_obs = RepoObs::new(row.get::<_, String>(0)?, 0);
+                repo_obs.id = row.get(1)?;
+                repo_obs.archived = row.get(2)?;
+                repo_obs.reconstructed = row.get(3)?;
+                for (idx, metric) in Metric::ALL.iter().enumerate() {
+                    repo_obs.set(*metric, row.get(idx + 4)?);
+                }
+                Ok(repo_obs)
+            })?
+            .collect::<rusqlite::Result<Vec<RepoObs>>>()?;
+        Ok(UserObs {
+            user_id: user_id.clone(),
+            time: to_time(timestamp)?,
+            counts,
+        })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn last_user_obs(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Option<UserObs>> {
+        let timestamp: Option<i64> = self
+            .con
+            .query_row(
+                "SELECT MAX(time) FROM user_obs WHERE owner=?1",
+                params![user_id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        timestamp
+            .map(|timestamp| self.read_user_obs_at(user_id, timestamp))
+            .transpose()
+    }
+    fn count_user_obs(
+        &self,
+        user_id: &UserId,
+    ) -> Result<usize> {
+        let count: usize = self.con.query_row(
+            "SELECT COUNT(*) FROM user_obs WHERE owner=?1",
+            params![user_id.to_string()],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+    fn extract_user(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<DatedObs>> {
+        if !self.has_user(user_id)? {
+            bail!("no data for user {}", user_id);
+        }
+        let mut stmt = self.con.prepare_cached(
+            "SELECT u.time, COALESCE(SUM(r.stars), 0) FROM user_obs u \
+             LEFT JOIN repo_obs r ON r.owner=u.owner AND r.time=u.time \
+             WHERE u.owner=?1 GROUP BY u.time",
+        )?;
+        let rows = stmt.query_map(params![user_id.to_string()], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, usize>(1)?))
+        })?;
+        let mut dated_obs = Vec::new();
+        for row in rows {
+            let (timestamp, stars) = row?;
+            dated_obs.push(DatedObs {
+                time: to_time(timestamp)?,
+                stars,
+            });
+        }
+        Ok(dated_obs)
+    }
+    fn extract_user_query(
+        &self,
+        user_id: &UserId,
+        repo_names: Vec<&str>,
+        metric: Metric,
+    ) -> Result<Vec<UserResponseLine>> {
+        if !self.has_user(user_id)? {
+            bail!("no data for user {}", user_id);
+        }
+        let mut lines: HashMap<i64, UserResponseLine> = HashMap::new();
+        // the metric name comes from our enum, it's safe to put it in the query
+        let mut stmt = self.con.prepare_cached(&format!(
+            "SELECT u.time, SUM(r.{0}), COALESCE(MAX(r.reconstructed), 0) FROM user_obs u \
+             LEFT JOIN repo_obs r ON r.owner=u.owner AND r.time=u.time \
+             WHERE u.owner=?1 GROUP BY u.time",
+            metric.name(),
+        ))?;
+        let rows = stmt.query_map(params![user_id.to_string()], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<usize>>(1)?,
+                row.get::<_, bool>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (timestamp, sum, reconstructed) = row?;
+            lines.insert(
+                timestamp,
+                UserResponseLine {
+                    time: to_time(timestamp)?,
+                    sum: sum.or_else(|| (metric == Metric::Stars).then_some(0)),
+                    counts: vec![None; repo_names.len()],
+                    reconstructed,
+                },
+            );
+        }
+        let mut stmt = self.con.prepare_cached(&format!(
+            "SELECT time, {} FROM repo_obs WHERE owner=?1 AND repo=?2",
+            metric.name(),
+        ))?;
+        for (idx, repo_name) in repo_names.iter().enumerate() {
+            let rows = stmt.query_map(params![user_id.to_string(), repo_name], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Option<usize>>(1)?))
+            })?;
+            for row in rows {
+                let (timestamp, count) = row?;
+                if let Some(line) = lines.get_mut(&timestamp) {
+                    line.counts[idx] = count;
+                }
+            }
+        }
+        Ok(lines.into_values().collect())
+    }
+    fn user_ids(&self) -> Result<Vec<UserId>> {
+        let mut stmt = self.con.prepare("SELECT DISTINCT owner FROM user_obs")?;
+        let logins = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(logins.iter().map(|login| UserId::parse(login)).collect())
+    }
+    fn user_obs_times(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<DateTime<Utc>>> {
+        let mut stmt = self
+            .con
+            .prepare_cached("SELECT time FROM user_obs WHERE owner=?1 ORDER BY time")?;
+        let timestamps = stmt
+            .query_map(params![user_id.to_string()], |row| row.get::<_, i64>(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+        timestamps.into_iter().map(to_time).collect()
+    }
+    fn read_user_obs(
+        &self,
+        user_id: &UserId,
+        time: DateTime<Utc>,
+    ) -> Result<UserObs> {
+        let timestamp = time.timestamp();
+        let exists: bool = self.con.query_row(
+            "SELECT EXISTS(SELECT 1 FROM user_obs WHERE owner=?1 AND time=?2)",
+            params![user_id.to_string(), timestamp],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            bail!("no observation of {} at {}", user_id, time);
+        }
+        self.read_user_obs_at(user_id, timestamp)
+    }
+    fn write_user_obs(
+        &self,
+        user_obs: &UserObs,
+        verbose: bool,
+    ) -> Result<()> {
+        let timestamp = user_obs.time.timestamp();
+        let tx = self.con.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO user_obs (owner, time) VALUES (?1, ?2)",
+            params![user_obs.user_id.to_string(), timestamp],
+        )?;
+        // a rewritten observation replaces the previous one, which
+        // may have had more repositories
+        tx.execute(
+            "DELETE FROM repo_obs WHERE owner=?1 AND time=?2",
+            params![user_obs.user_id.to_string(), timestamp],
+        )?;
+        {
+            let mut stmt = tx.prepare_cached(&format!(
+                "INSERT OR REPLACE INTO repo_obs (owner, repo, time, id, archived, reconstructed, {}) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                metric_columns(),
+            ))?;
+            for repo_obs in &user_obs.counts {
+                stmt.execute(params![
+                    user_obs.user_id.to_string(),
+                    &repo_obs.repo_name,
+                    timestamp,
+                    &repo_obs.id,
+                    repo_obs.archived,
+                    repo_obs.reconstructed,
+                    repo_obs.stars,
+                    repo_obs.forks,
+                    repo_obs.watchers,
+                    repo_obs.issues,
+                    repo_obs.prs,
+                    repo_obs.downloads,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        if verbose {
+            println!(
+                "wrote {} rows for {} in database",
+                user_obs.counts.len(),
+                &user_obs.user_id,
+            );
+        }
+        Ok(())
+    }
+    fn write_heartbeat(
+        &self,
+        user_id: &UserId,
+        heartbeat: &Heartbeat,
+    ) -> Result<()> {
+        self.con.execute(
+            "INSERT OR REPLACE INTO heartbeat (owner, time, status) VALUES (?1, ?2, ?3)",
+            params![
+                user_id.to_string(),
+                heartbeat.time.timestamp(),
+                serde_json::to_value(heartbeat.status)?.as_str(),
+            ],
+        )?;
+        Ok(())
+    }
+    fn heartbeats(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<Heartbeat>> {
+        let mut stmt = self
+            .con
+            .prepare_cached("SELECT time, status FROM heartbeat WHERE owner=?1 ORDER BY time")?;
+        let rows = stmt.query_map(params![user_id.to_string()], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut heartbeats = Vec::new();
+        for row in rows {
+            let (timestamp, status) = row?;
+            heartbeats.push(Heartbeat {
+                time: to_time(timestamp)?,
+                status: serde_json::from_value(status.into())?,
+            });
+        }
+        Ok(heartbeats)
+    }
+    fn write_follow_obs(
+        &self,
+        user_id: &UserId,
+        follow_obs: &FollowObs,
+    ) -> Result<()> {
+        self.con.execute(
+            "INSERT OR REPLACE INTO follow_obs (owner, time, followers, following) \
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                user_id.to_string(),
+                follow_obs.time.timestamp(),
+                follow_obs.followers,
+                follow_obs.following,
+            ],
+        )?;
+        Ok(())
+    }
+    fn follow_obss(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<FollowObs>> {
+        let mut stmt = self.con.prepare_cached(
+            "SELECT time, followers, following FROM follow_obs WHERE owner=?1 ORDER BY time",
+        )?;
+        let rows = stmt.query_map(params![user_id.to_string()], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, usize>(1)?,
+                row.get::<_, usize>(2)?,
+            ))
+        })?;
+        let mut follow_obss = Vec::new();
+        for row in rows {
+            let (timestamp, followers, following) = row?;
+            follow_obss.push(FollowObs {
+                time: to_time(timestamp)?,
+                followers,
+                following,
+            });
+        }
+        Ok(follow_obss)
+    }
+    fn write_stargazer_events(
+        &self,
+        repo_id: &RepoId,
+        events: &[StargazerEvent],
+    ) -> Result<()> {
+        let tx = self.con.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO stargazer_event (owner, repo, time, login, kind) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for event in events {
+                stmt.execute(params![
+                    repo_id.owner.to_string(),
+                    &repo_id.name,
+                    event.time.timestamp(),
+                    &event.login,
+                    event.kind.to_string(),
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+    fn stargazer_events(
+        &self,
+        repo_id: &RepoId,
+    ) -> Result<Vec<StargazerEvent>> {
+        let mut stmt = self.con.prepare_cached(
+            "SELECT time, login, kind FROM stargazer_event WHERE owner=?1 AND repo=?2 ORDER BY time",
+        )?;
+        let rows = stmt.query_map(params![repo_id.owner.to_string(), &repo_id.name], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut events = Vec::new();
+        for row in rows {
+            let (timestamp, login, kind) = row?;
+            events.push(StargazerEvent {
+                time: to_time(timestamp)?,
+                login,
+                kind: serde_json::from_value(kind.into())?,
+            });
+        }
+        Ok(events)
+    }
+    fn stargazer_repo_names(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<String>> {
+        let mut stmt = self.con.prepare_cached(
+            "SELECT DISTINCT repo FROM stargazer_event WHERE owner=?1 ORDER BY repo",
+        )?;
+        let names = stmt
+            .query_map(params![user_id.to_string()], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(names)
+    }
+    fn write_starred(
+        &self,
+        user_id: &UserId,
+        starred: &[StarredRepo],
+    ) -> Result<()> {
+        let tx = self.con.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO starred (owner, time, repo, stars) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for starred_repo in starred {
+                stmt.execute(params![
+                    user_id.to_string(),
+                    starred_repo.starred_at.timestamp(),
+                    &starred_repo.repo,
+                    starred_repo.stars,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+    fn starred(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<StarredRepo>> {
+        let mut stmt = self.con.prepare_cached(
+            "SELECT time, repo, stars FROM starred WHERE owner=?1 ORDER BY time, repo",
+        )?;
+        let rows = stmt.query_map(params![user_id.to_string()], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, usize>(2)?,
+            ))
+        })?;
+        let mut starred = Vec::new();
+        for row in rows {
+            let (timestamp, repo, stars) = row?;
+            starred.push(StarredRepo {
+                starred_at: to_time(timestamp)?,
+                repo,
+                stars,
+            });
+        }
+        Ok(starred)
+    }
+}
@@ -0,0 +1,105 @@
+use {
+    crate::*,
+    chrono::{
+        DateTime,
+        SecondsFormat,
+        Utc,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    std::{
+        collections::HashSet,
+        fmt,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StargazerEventKind {
+    /// the user starred the repository
+    Star,
+    /// the user isn't a stargazer anymore (unstarred, or was deleted)
+    Unstar,
+}
+
+/// a change in the stargazers of a tracked repository
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct StargazerEvent {
+    /// when the star was given, or when its removal was noticed
+    pub time: DateTime<Utc>,
+    pub login: String,
+    pub kind: StargazerEventKind,
+}
+
+impl fmt::Display for StargazerEventKind {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            Self::Star => write!(f, "star"),
+            Self::Unstar => write!(f, "unstar"),
+        }
+    }
+}
+
+/// the events as a table, one row per event
+pub fn stargazer_events_table(events: &[StargazerEvent]) -> Table {
+    let columns = ["time", "login", "event"];
+    let mut table = Table::new(columns.iter().map(|c| c.to_string()).collect());
+    for event in events {
+        table.add_row(vec![
+            event.time.to_rfc3339_opts(SecondsFormat::Secs, true).into(),
+            event.login.clone().into(),
+            event.kind.to_string().into(),
+        ]);
+    }
+    table
+}
+
+/// the logins of the current stargazers, according to the events,
+/// which must be sorted by time
+pub fn current_stargazers(events: &[StargazerEvent]) -> HashSet<String> {
+    let mut stargazers = HashSet::new();
+    for event in events {
+        match event.kind {
+            StargazerEventKind::Star => stargazers.insert(event.login.clone()),
+            StargazerEventKind::Unstar => stargazers.remove(&event.login),
+        };
+    }
+    stargazers
+}
+
+/// compute the events turning the known stargazers into the ones of
+/// the star history: new stars are dated from when they were given,
+/// removed ones from now
+pub fn stargazer_events(
+    known: &HashSet<String>,
+    history: &StarHistory,
+    now: DateTime<Utc>,
+) -> Vec<StargazerEvent> {
+    let mut events: Vec<StargazerEvent> = history
+        .stars
+        .iter()
+        .filter(|star| !known.contains(&star.login))
+        .map(|star| StargazerEvent {
+            time: star.starred_at,
+            login: star.login.clone(),
+            kind: StargazerEventKind::Star,
+        })
+        .collect();
+    let current: HashSet<&str> = history.stars.iter().map(|s| s.login.as_str()).collect();
+    let mut departed: Vec<&String> = known
+        .iter()
+        .filter(|login| !current.contains(login.as_str()))
+        .collect();
+    departed.sort();
+    events.extend(departed.into_iter().map(|login| StargazerEvent {
+        time: now,
+        login: login.clone(),
+        kind: StargazerEventKind::Unstar,
+    }));
+    events
+}
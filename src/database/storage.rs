@@ -0,0 +1,180 @@
+use {
+    crate::*,
+    anyhow::*,
+    chrono::{
+        DateTime,
+        Utc,
+    },
+    std::{
+        fmt,
+        path::Path,
+        str::FromStr,
+    },
+};
+
+/// the key, in conf, of the storage kind (eg `starry set storage sqlite`)
+pub static STORAGE_KEY: &str = "storage";
+
+/// the operations any database layout must provide
+pub trait Storage: fmt::Debug {
+    /// return the most recent observation of a user, if any
+    fn last_user_obs(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Option<UserObs>>;
+    /// return the number of observations of a user
+    fn count_user_obs(
+        &self,
+        user_id: &UserId,
+    ) -> Result<usize>;
+    /// read the time serie made of the total numbers of stars
+    /// of a user per date. Records are not sorted by date.
+    fn extract_user(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<DatedObs>>;
+    /// fetch and return the (unordered) lines, one per time,
+    /// with the values of the metric for the given user query
+    fn extract_user_query(
+        &self,
+        user_id: &UserId,
+        repo_names: Vec<&str>,
+        metric: Metric,
+    ) -> Result<Vec<UserResponseLine>>;
+    /// return the ids of all users having observations
+    fn user_ids(&self) -> Result<Vec<UserId>>;
+    /// return the times of all observations of a user, sorted
+    fn user_obs_times(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<DateTime<Utc>>>;
+    /// read the observation of a user at a given time
+    fn read_user_obs(
+        &self,
+        user_id: &UserId,
+        time: DateTime<Utc>,
+    ) -> Result<UserObs>;
+    /// store a user observation
+    fn write_user_obs(
+        &self,
+        user_obs: &UserObs,
+        verbose: bool,
+    ) -> Result<()>;
+    /// record that a user was checked (or failed to be)
+    fn write_heartbeat(
+        &self,
+        user_id: &UserId,
+        heartbeat: &Heartbeat,
+    ) -> Result<()>;
+    /// return all heartbeats of a user, sorted
+    fn heartbeats(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<Heartbeat>>;
+    /// record changes in the stargazers of a repository
+    fn write_stargazer_events(
+        &self,
+        repo_id: &RepoId,
+        events: &[StargazerEvent],
+    ) -> Result<()>;
+    /// return all stargazer events of a repository, sorted
+    fn stargazer_events(
+        &self,
+        repo_id: &RepoId,
+    ) -> Result<Vec<StargazerEvent>>;
+    /// return the names of the repositories of an owner
+    /// having stargazer events
+    fn stargazer_repo_names(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<String>>;
+    /// record the numbers of followers and followed users of a user
+    fn write_follow_obs(
+        &self,
+        user_id: &UserId,
+        follow_obs: &FollowObs,
+    ) -> Result<()>;
+    /// return all the follow observations of a user, sorted
+    fn follow_obss(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<FollowObs>>;
+    /// record repositories newly starred by a user
+    fn write_starred(
+        &self,
+        user_id: &UserId,
+        starred: &[StarredRepo],
+    ) -> Result<()>;
+    /// return the recorded repositories starred by a user, sorted by date
+    fn starred(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<StarredRepo>>;
+}
+
+/// the available database layouts
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum StorageKind {
+    /// one CSV file per (user, time)
+    #[default]
+    Csv,
+    /// CSV files, most of them holding only the changes
+    /// since the previous observation
+    CsvDelta,
+    /// a single SQLite file
+    Sqlite,
+}
+
+impl StorageKind {
+    pub fn from_conf(conf: &Conf) -> Result<Self> {
+        conf.get(STORAGE_KEY)
+            .map_or(Ok(Self::default()), |s| s.parse())
+    }
+    /// tell whether both kinds store observations in the same files
+    pub fn shares_files_with(
+        self,
+        other: Self,
+    ) -> bool {
+        self == other || (self != Self::Sqlite && other != Self::Sqlite)
+    }
+    /// open (and create if needed) the storage of this kind
+    /// in the given data directory
+    pub fn open(
+        self,
+        data_dir: &Path,
+    ) -> Result<Box<dyn Storage>> {
+        Ok(match self {
+            Self::Csv => Box::new(CsvStorage::new(data_dir, false)),
+            Self::CsvDelta => Box::new(CsvStorage::new(data_dir, true)),
+            Self::Sqlite => Box::new(SqliteStorage::open(data_dir)?),
+        })
+    }
+}
+
+impl FromStr for StorageKind {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_ref() {
+            "csv" => Ok(Self::Csv),
+            "csv-delta" => Ok(Self::CsvDelta),
+            "sqlite" => Ok(Self::Sqlite),
+            _ => bail!(
+                "Unknown storage: {:?} (expected 'csv', 'csv-delta' or 'sqlite')",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for StorageKind {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            Self::Csv => write!(f, "csv"),
+            Self::CsvDelta => write!(f, "csv-delta"),
+            Self::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
@@ -28,11 +28,43 @@ pub struct UserObs {
     pub counts: Vec<RepoObs>,
 }
 
-/// a line in a csv file whose path already contains the user login and date
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// a line in a csv file whose path already contains the user login and date.
+///
+/// Metrics other than stars are present only when they were fetched, and
+/// there are columns in the file only for the fields which are present.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct RepoObs {
     pub repo_name: String,
+    /// the stable id given by GitHub, which survives renames and transfers
+    #[serde(default)]
+    pub id: Option<String>,
     pub stars: usize,
+    #[serde(default)]
+    pub forks: Option<usize>,
+    #[serde(default)]
+    pub watchers: Option<usize>,
+    #[serde(default)]
+    pub issues: Option<usize>,
+    #[serde(default)]
+    pub prs: Option<usize>,
+    #[serde(default)]
+    pub downloads: Option<usize>,
+    #[serde(default)]
+    pub archived: Option<bool>,
+    /// whether the observation was synthesized from the dates of the
+    /// stars (by a backfill) instead of being really observed
+    #[serde(default)]
+    pub reconstructed: Option<bool>,
+}
+
+/// the columns of a csv file of repo observations: only
+/// the optional fields having a value in some line get a column
+#[derive(Debug, Clone)]
+pub struct CsvColumns {
+    pub id: bool,
+    pub metrics: Vec<Metric>,
+    pub archived: bool,
+    pub reconstructed: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -41,6 +73,122 @@ pub struct DatedObs {
     pub stars: usize,
 }
 
+impl RepoObs {
+    pub fn new<S: Into<String>>(
+        repo_name: S,
+        stars: usize,
+    ) -> Self {
+        Self {
+            repo_name: repo_name.into(),
+            stars,
+            ..Default::default()
+        }
+    }
+    pub fn get(
+        &self,
+        metric: Metric,
+    ) -> Option<usize> {
+        match metric {
+            Metric::Stars => Some(self.stars),
+            Metric::Forks => self.forks,
+            Metric::Watchers => self.watchers,
+            Metric::Issues => self.issues,
+            Metric::Prs => self.prs,
+            Metric::Downloads => self.downloads,
+        }
+    }
+    /// set the value of a metric (stars are only changed by a value)
+    pub fn set(
+        &mut self,
+        metric: Metric,
+        value: Option<usize>,
+    ) {
+        match metric {
+            Metric::Stars => {
+                if let Some(stars) = value {
+                    self.stars = stars;
+                }
+            }
+            Metric::Forks => self.forks = value,
+            Metric::Watchers => self.watchers = value,
+            Metric::Issues => self.issues = value,
+            Metric::Prs => self.prs = value,
+            Metric::Downloads => self.downloads = value,
+        }
+    }
+}
+
+impl CsvColumns {
+    /// compute the columns needed for the given observations
+    pub fn for_obs<'o, I: IntoIterator<Item = &'o RepoObs>>(counts: I) -> Self {
+        let mut id = false;
+        let mut present = vec![false; Metric::ALL.len()];
+        present[0] = true; // stars are always there
+        let mut archived = false;
+        let mut reconstructed = false;
+        for repo_obs in counts {
+            id |= repo_obs.id.is_some();
+            for (idx, metric) in Metric::ALL.iter().enumerate() {
+                present[idx] |= repo_obs.get(*metric).is_some();
+            }
+            archived |= repo_obs.archived.is_some();
+            reconstructed |= repo_obs.reconstructed.is_some();
+        }
+        let metrics = Metric::ALL
+            .iter()
+            .zip(present)
+            .filter_map(|(metric, present)| present.then_some(*metric))
+            .collect();
+        Self {
+            id,
+            metrics,
+            archived,
+            reconstructed,
+        }
+    }
+    /// the number of columns
+    pub fn count(&self) -> usize {
+        1 + self.id as usize
+            + self.metrics.len()
+            + self.archived as usize
+            + self.reconstructed as usize
+    }
+    pub fn header(&self) -> Vec<&'static str> {
+        let mut header = vec!["repo_name"];
+        if self.id {
+            header.push("id");
+        }
+        header.extend(self.metrics.iter().map(|m| m.name()));
+        if self.archived {
+            header.push("archived");
+        }
+        if self.reconstructed {
+            header.push("reconstructed");
+        }
+        header
+    }
+    pub fn record(
+        &self,
+        repo_obs: &RepoObs,
+    ) -> Vec<String> {
+        fn cell<T: ToString>(value: Option<T>) -> String {
+            value.map_or(String::new(), |v| v.to_string())
+        }
+        let mut record = vec![repo_obs.repo_name.clone()];
+        if self.id {
+            record.push(cell(repo_obs.id.as_ref()));
+        }
+        record.extend(self.metrics.iter().map(|m| cell(repo_obs.get(*m))));
+        if self.archived {
+            record.push(cell(repo_obs.archived));
+        }
+        if self.reconstructed {
+            record.push(cell(repo_obs.reconstructed));
+        }
+        record
+    }
+}
+
 impl UserObs {
     pub fn write_in_dir(
         &self,
@@ -48,14 +196,12 @@ impl UserObs {
         verbose: bool,
     ) -> Result<()> {
         fs::create_dir_all(user_dir)?;
-        // the chosen format, precise to the seconds only, avoids having
-        // a dot in the name (which would break the naming with the ext)
-        let file_path = user_dir
-            .join(self.time.to_rfc3339_opts(SecondsFormat::Secs, true))
-            .with_extension("csv");
+        let file_path = user_dir.join(Self::filename(self.time));
+        let columns = CsvColumns::for_obs(&self.counts);
         let mut w = csv::Writer::from_path(&file_path)?;
+        w.write_record(columns.header())?;
         for repo_obs in &self.counts {
-            w.serialize(repo_obs)?;
+            w.write_record(columns.record(repo_obs))?;
         }
         w.flush()?;
         if verbose {
@@ -63,6 +209,12 @@ impl UserObs {
         }
         Ok(())
     }
+    /// the name of the file of a user observation at this time
+    pub fn filename(time: DateTime<Utc>) -> String {
+        // the chosen format, precise to the seconds only, avoids having
+        // a dot in the name (which would break the naming with the ext)
+        format!("{}.csv", time.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
     /// if the file has a name matching the userobs filename
     /// format (date.csv), then returns this date
     pub fn filename_date(path: &Path) -> Option<DateTime<Utc>> {
@@ -131,16 +283,51 @@ impl UserObs {
             stars: self.counts.iter().map(|rc| rc.stars).sum(),
         }
     }
+    /// return the sum of a metric over all repos, or none
+    /// if no repo has a value for this metric
+    pub fn sum_metric(
+        &self,
+        metric: Metric,
+    ) -> Option<usize> {
+        self.counts
+            .iter()
+            .filter_map(|rc| rc.get(metric))
+            .fold(None, |sum, v| Some(sum.unwrap_or(0) + v))
+            .or_else(|| (metric == Metric::Stars).then_some(0))
+    }
+    /// whether the observation was synthesized by a backfill
+    pub fn is_reconstructed(&self) -> bool {
+        self.counts
+            .iter()
+            .any(|repo_obs| repo_obs.reconstructed == Some(true))
+    }
+    pub fn repo(
+        &self,
+        repo_name: &str,
+    ) -> Option<&RepoObs> {
+        self.counts
+            .iter()
+            .find(|repo_obs| repo_obs.repo_name == repo_name)
+    }
     pub fn repo_count(
         &self,
         repo_name: &str,
     ) -> Option<usize> {
-        for repo_obs in &self.counts {
-            if repo_obs.repo_name == repo_name {
-                return Some(repo_obs.stars);
+        self.repo(repo_name).map(|repo_obs| repo_obs.stars)
+    }
+    /// find the observation of the same repo in this user observation:
+    /// by id when known, by name otherwise
+    pub fn matching_repo(
+        &self,
+        repo_obs: &RepoObs,
+    ) -> Option<&RepoObs> {
+        if let Some(id) = &repo_obs.id {
+            if let Some(found) = self.counts.iter().find(|ro| ro.id.as_ref() == Some(id)) {
+                return Some(found);
             }
         }
-        None
+        self.repo(&repo_obs.repo_name)
+            .filter(|ro| ro.id.is_none() || repo_obs.id.is_none())
     }
     pub fn diff_from(
         &self,
@@ -148,16 +335,38 @@ impl UserObs {
     ) -> Vec<RepoChange> {
         let mut changes = Vec::new();
         for repo_obs in &self.counts {
-            let old_stars = old_uo.repo_count(&repo_obs.repo_name);
-            if old_stars == Some(repo_obs.stars) {
-                continue;
-            }
+            let old_repo_obs = old_uo.matching_repo(repo_obs);
+            let kind = match old_repo_obs {
+                Some(old) if old.repo_name != repo_obs.repo_name => ChangeKind::Renamed {
+                    old_name: old.repo_name.clone(),
+                },
+                Some(old) if old.archived == Some(false) && repo_obs.archived == Some(true) => {
+                    ChangeKind::Archived
+                }
+                Some(old) if old.stars == repo_obs.stars => {
+                    continue;
+                }
+                _ => ChangeKind::Stars,
+            };
             changes.push(RepoChange {
                 repo_id: RepoId::new(self.user_id.clone(), &repo_obs.repo_name),
-                old_stars,
-                new_stars: repo_obs.stars,
+                kind,
+                id: repo_obs.id.clone(),
+                old_stars: old_repo_obs.map(|old| old.stars),
+                new_stars: Some(repo_obs.stars),
             });
         }
+        for old_repo_obs in &old_uo.counts {
+            if self.matching_repo(old_repo_obs).is_none() {
+                changes.push(RepoChange {
+                    repo_id: RepoId::new(self.user_id.clone(), &old_repo_obs.repo_name),
+                    kind: ChangeKind::Disappeared,
+                    id: old_repo_obs.id.clone(),
+                    old_stars: Some(old_repo_obs.stars),
+                    new_stars: None,
+                });
+            }
+        }
         changes
     }
 }
@@ -15,9 +15,46 @@ pub(crate) struct UserQuery {
     pub repos: Vec<Col>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct UserResponseLine {
     pub time: DateTime<Utc>,
-    pub sum: usize,
+    /// the sum over all repos, if any repo has a value for the metric
+    pub sum: Option<usize>,
     pub counts: Vec<Option<usize>>,
+    /// whether the observation was synthesized by a backfill
+    pub reconstructed: bool,
+}
+
+/// add, at every successful heartbeat without observation, a copy
+/// of the previous line (the stars didn't change since).
+/// Returned lines are sorted by time.
+pub fn carry_forward(
+    mut lines: Vec<UserResponseLine>,
+    heartbeats: &[Heartbeat],
+) -> Vec<UserResponseLine> {
+    fn push_copy(
+        filled: &mut Vec<UserResponseLine>,
+        time: DateTime<Utc>,
+    ) {
+        if let Some(previous) = filled.last() {
+            if previous.time < time {
+                let mut copy = previous.clone();
+                copy.time = time;
+                filled.push(copy);
+            }
+        }
+    }
+    lines.sort_by_key(|line| line.time);
+    let mut filled: Vec<UserResponseLine> = Vec::with_capacity(lines.len());
+    let mut heartbeats = heartbeats.iter().filter(|h| h.is_ok()).peekable();
+    for line in lines {
+        while let Some(heartbeat) = heartbeats.next_if(|h| h.time < line.time) {
+            push_copy(&mut filled, heartbeat.time);
+        }
+        filled.push(line);
+    }
+    for heartbeat in heartbeats {
+        push_copy(&mut filled, heartbeat.time);
+    }
+    filled
 }
@@ -0,0 +1,301 @@
+use {
+    crate::*,
+    anyhow::{
+        anyhow,
+        bail,
+        Result,
+    },
+    chrono::{
+        DateTime,
+        Utc,
+    },
+    futures::future::BoxFuture,
+    serde::Deserialize,
+    std::{
+        collections::HashMap,
+        fs,
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+};
+
+/// the prop key for the directory of the fixtures replacing the forges
+pub static FIXTURES_KEY: &str = "fixtures";
+
+/// the env var which, when set, overrides the `fixtures` prop
+pub static FIXTURES_ENV_VAR: &str = "STARRY_FIXTURES";
+
+/// return the directory of fixtures replacing the forges, if any
+pub fn fixtures_dir(conf: &Conf) -> Option<PathBuf> {
+    std::env::var_os(FIXTURES_ENV_VAR)
+        .map(PathBuf::from)
+        .or_else(|| conf.get(FIXTURES_KEY).map(PathBuf::from))
+}
+
+/// a forge reading the repositories of owners from files instead of
+/// querying an API, so that starry can run without network (in tests).
+///
+/// The repositories of an owner are in `users/<owner>.csv` or
+/// `organizations/<owner>.csv`, in the same format as the CSV
+/// storage, owners being qualified with their host when they're
+/// not on github.com (eg `users/someone@gitlab.com.csv`).
+///
+/// The stargazers of a repository, with the dates of their stars,
+/// are in `stargazers/<owner>/<repo>.csv` (columns `login,starred_at`),
+/// and the repositories starred by a user in `starred/<user>.csv`
+/// (columns `starred_at,repo,stars`). The numbers of followers and
+/// followed users of a user are in `follows/<user>.csv` (columns
+/// `followers,following`).
+pub struct FixtureClient {
+    host: Host,
+    dir: PathBuf,
+    /// the metrics to fetch in addition to stars
+    metrics: Vec<Metric>,
+}
+
+impl FixtureClient {
+    pub fn new(
+        conf: &Conf,
+        host: Host,
+        dir: PathBuf,
+    ) -> Result<Self> {
+        let metrics = conf.metrics()?;
+        Ok(Self { host, dir, metrics })
+    }
+    fn kind_dir(
+        &self,
+        kind: OwnerKind,
+    ) -> PathBuf {
+        match kind {
+            OwnerKind::User => self.dir.join("users"),
+            OwnerKind::Organization => self.dir.join("organizations"),
+        }
+    }
+    /// return the fixture file of an owner, if there's one
+    fn owner_file(
+        &self,
+        user_id: &UserId,
+    ) -> Option<PathBuf> {
+        let path = self.kind_dir(user_id.kind).join(format!("{}.csv", user_id));
+        path.exists().then_some(path)
+    }
+    fn read_owner(
+        &self,
+        user_id: &UserId,
+        now: DateTime<Utc>,
+    ) -> Result<UserObs> {
+        let path = self
+            .owner_file(user_id)
+            .ok_or_else(|| anyhow!("No fixture for {} {}", user_id.kind, user_id))?;
+        let mut user_obs = UserObs::read_file(&path, user_id.clone(), now)?;
+        user_obs.counts = user_obs
+            .counts
+            .into_iter()
+            .map(|repo_obs| retain_metrics(repo_obs, &self.metrics))
+            .collect();
+        Ok(user_obs)
+    }
+    /// read the numbers of followers and followed users of a user,
+    /// if there's a fixture for them
+    fn read_follow_counts(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Option<FollowCounts>> {
+        let path = self.dir.join("follows").join(format!("{}.csv", user_id));
+        if !path.exists() {
+            return Ok(None);
+        }
+        let follow_counts = csv::Reader::from_path(&path)?
+            .deserialize()
+            .next()
+            .transpose()?;
+        Ok(follow_counts)
+    }
+    /// read all the fixture files of this host's owners
+    fn read_all(&self) -> Result<Vec<UserObs>> {
+        let mut user_obss = Vec::new();
+        for kind in [OwnerKind::User, OwnerKind::Organization] {
+            let dir = self.kind_dir(kind);
+            if !dir.exists() {
+                continue;
+            }
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                let Some(name) = owner_name(&path) else {
+                    continue;
+                };
+                let mut user_id = UserId::parse(name);
+                if user_id.host != self.host.name {
+                    continue;
+                }
+                user_id.kind = kind;
+                user_obss.push(self.read_owner(&user_id, Utc::now())?);
+            }
+        }
+        Ok(user_obss)
+    }
+}
+
+/// a line of a stargazers fixture file
+#[derive(Debug, Deserialize)]
+struct StarRecord {
+    login: String,
+    starred_at: DateTime<Utc>,
+}
+
+/// the owner name of a fixture file, with its host if any
+fn owner_name(path: &Path) -> Option<&str> {
+    path.file_name()?.to_str()?.strip_suffix(".csv")
+}
+
+impl Forge for FixtureClient {
+    fn host(&self) -> &Host {
+        &self.host
+    }
+    fn get_owner_kind<'a>(
+        &'a self,
+        login: &'a str,
+    ) -> BoxFuture<'a, Result<OwnerKind>> {
+        Box::pin(async move {
+            [OwnerKind::User, OwnerKind::Organization]
+                .iter()
+                .copied()
+                .find(|&kind| {
+                    let user_id = UserId::with_kind(login, kind).on_host(self.host.name.clone());
+                    self.owner_file(&user_id).is_some()
+                })
+                .ok_or_else(|| anyhow!("No user or organization found with login {:?}", login))
+        })
+    }
+    fn get_user(
+        &self,
+        user_id: UserId,
+    ) -> BoxFuture<'_, Result<User>> {
+        Box::pin(async move {
+            let user_obs = self.read_owner(&user_id, Utc::now())?;
+            Ok(User {
+                name: user_id.login.clone(),
+                follow_counts: self.read_follow_counts(&user_id)?,
+                user_id,
+                non_fork_repositories_count: user_obs.counts.len(),
+            })
+        })
+    }
+    fn get_user_star_counts(
+        &self,
+        user_id: UserId,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<UserObs>> {
+        Box::pin(async move { self.read_owner(&user_id, now) })
+    }
+    fn get_repo_stars<'a>(
+        &'a self,
+        repo_id: &'a RepoId,
+    ) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move {
+            let mut owner = repo_id.owner.clone();
+            if self.owner_file(&owner).is_none() {
+                owner.kind = self.get_owner_kind(&owner.login).await?;
+            }
+            self.read_owner(&owner, Utc::now())?
+                .repo(&repo_id.name)
+                .map(|repo_obs| repo_obs.stars)
+                .ok_or_else(|| anyhow!("Repository {} not found", repo_id))
+        })
+    }
+    fn get_repos_star_counts<'a>(
+        &'a self,
+        repo_ids: &'a [RepoId],
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<UserObs>>> {
+        Box::pin(async move {
+            let all = self.read_all()?;
+            let mut repo_obss = Vec::new();
+            for repo_id in repo_ids {
+                let repo_obs = all
+                    .iter()
+                    .filter(|uo| uo.user_id.login == repo_id.owner.login)
+                    .find_map(|uo| uo.repo(&repo_id.name));
+                if repo_obs.is_none() {
+                    eprintln!("Repository {} not found", repo_id);
+                }
+                repo_obss.push((repo_id, repo_obs.cloned()));
+            }
+            Ok(owner_obss(repo_obss, now))
+        })
+    }
+    fn get_star_history<'a>(
+        &'a self,
+        repo_id: &'a RepoId,
+    ) -> BoxFuture<'a, Result<StarHistory>> {
+        Box::pin(async move {
+            let path = self
+                .dir
+                .join("stargazers")
+                .join(repo_id.owner.to_string())
+                .join(format!("{}.csv", repo_id.name));
+            if !path.exists() {
+                bail!("No stargazers fixture for {}", repo_id);
+            }
+            let mut stars = Vec::new();
+            for record in csv::Reader::from_path(&path)?.deserialize() {
+                let record: StarRecord = record?;
+                stars.push(Star {
+                    login: record.login,
+                    starred_at: record.starred_at,
+                });
+            }
+            stars.sort_by_key(|star| star.starred_at);
+            Ok(StarHistory {
+                created_at: None,
+                stars,
+            })
+        })
+    }
+    fn get_follow_counts<'a>(
+        &'a self,
+        user_id: &'a UserId,
+    ) -> BoxFuture<'a, Result<Option<FollowCounts>>> {
+        Box::pin(async move { self.read_follow_counts(user_id) })
+    }
+    fn get_starred_repos<'a>(
+        &'a self,
+        user_id: &'a UserId,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<StarredRepo>>> {
+        Box::pin(async move {
+            let path = self.dir.join("starred").join(format!("{}.csv", user_id));
+            if !path.exists() {
+                bail!("No starred fixture for {}", user_id);
+            }
+            let mut starred = Vec::new();
+            for record in csv::Reader::from_path(&path)?.deserialize() {
+                let record: StarredRepo = record?;
+                if since.is_none_or(|since| record.starred_at > since) {
+                    starred.push(record);
+                }
+            }
+            starred.sort_by_key(|s| s.starred_at);
+            Ok(starred)
+        })
+    }
+    fn locate_repos<'a>(
+        &'a self,
+        ids: &'a [String],
+    ) -> BoxFuture<'a, Result<HashMap<String, (RepoId, usize)>>> {
+        Box::pin(async move {
+            let mut located = HashMap::new();
+            for user_obs in self.read_all()? {
+                for repo_obs in &user_obs.counts {
+                    if let Some(id) = repo_obs.id.as_ref().filter(|id| ids.contains(id)) {
+                        let repo_id = RepoId::new(user_obs.user_id.clone(), &repo_obs.repo_name);
+                        located.insert(id.clone(), (repo_id, repo_obs.stars));
+                    }
+                }
+            }
+            Ok(located)
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use {
+    anyhow::*,
+    std::{
+        fmt,
+        str::FromStr,
+    },
+};
+
+/// the prop key for the kind of forge of a host (eg `forge@git.example.com`)
+pub static FORGE_KEY: &str = "forge";
+
+/// the kind of software running a host
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ForgeKind {
+    #[default]
+    Github,
+    Gitlab,
+    /// Gitea, or its fork Forgejo
+    Gitea,
+}
+
+impl ForgeKind {
+    /// the kind of the well known hosts, when it's not GitHub
+    pub fn of_known_host(host: &str) -> Option<Self> {
+        match host {
+            "gitlab.com" => Some(Self::Gitlab),
+            "codeberg.org" | "gitea.com" => Some(Self::Gitea),
+            _ => None,
+        }
+    }
+    /// the URL of the API of a named host of this kind
+    pub fn default_api_url(
+        self,
+        host: &str,
+    ) -> String {
+        match self {
+            Self::Github => format!("https://{}/api/graphql", host),
+            Self::Gitlab => format!("https://{}/api/v4", host),
+            Self::Gitea => format!("https://{}/api/v1", host),
+        }
+    }
+}
+
+impl FromStr for ForgeKind {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "github" => Ok(Self::Github),
+            "gitlab" => Ok(Self::Gitlab),
+            "gitea" | "forgejo" => Ok(Self::Gitea),
+            _ => Err(anyhow!(
+                "Unknown forge: {:?} (expected one of github, gitlab, gitea, forgejo)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ForgeKind {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            Self::Github => write!(f, "github"),
+            Self::Gitlab => write!(f, "gitlab"),
+            Self::Gitea => write!(f, "gitea"),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use {
+    crate::*,
+    anyhow::{
+        bail,
+        Result,
+    },
+    chrono::{
+        DateTime,
+        Utc,
+    },
+    futures::future::BoxFuture,
+    std::{
+        collections::HashMap,
+        sync::Arc,
+    },
+};
+
+/// what starry needs from a forge (GitHub, GitLab, Gitea, etc.)
+///
+/// Futures are boxed so that forges can be used as trait
+/// objects and their queries spawned.
+pub trait Forge: Send + Sync {
+    /// the instance this client queries
+    fn host(&self) -> &Host;
+    /// find whether a login is the one of a user or of an organization
+    fn get_owner_kind<'a>(
+        &'a self,
+        login: &'a str,
+    ) -> BoxFuture<'a, Result<OwnerKind>>;
+    /// get an owner's basic information
+    fn get_user(
+        &self,
+        user_id: UserId,
+    ) -> BoxFuture<'_, Result<User>>;
+    /// get a UserObs with the counts of all the non fork
+    /// repositories of an owner
+    fn get_user_star_counts(
+        &self,
+        user_id: UserId,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<UserObs>>;
+    /// get the current number of stars of a repository
+    fn get_repo_stars<'a>(
+        &'a self,
+        repo_id: &'a RepoId,
+    ) -> BoxFuture<'a, Result<usize>>;
+    /// get the counts of some repositories, grouped in one UserObs
+    /// per owner. Repositories which can't be found are reported
+    /// and skipped.
+    fn get_repos_star_counts<'a>(
+        &'a self,
+        repo_ids: &'a [RepoId],
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<UserObs>>>;
+    /// find where repositories are now, given their ids, and how
+    /// many stars they have. Forges which can't do it find nothing.
+    fn locate_repos<'a>(
+        &'a self,
+        _ids: &'a [String],
+    ) -> BoxFuture<'a, Result<HashMap<String, (RepoId, usize)>>> {
+        Box::pin(async { Ok(HashMap::new()) })
+    }
+    /// get the current stars of a repository, with when they were
+    /// given. Forges which don't tell it fail.
+    fn get_star_history<'a>(
+        &'a self,
+        repo_id: &'a RepoId,
+    ) -> BoxFuture<'a, Result<StarHistory>> {
+        Box::pin(async move {
+            bail!(
+                "{} doesn't tell when the stars of {} were given",
+                self.host(),
+                repo_id
+            )
+        })
+    }
+    /// get the numbers of followers and followed users of a user.
+    /// Forges which can't tell return none.
+    fn get_follow_counts<'a>(
+        &'a self,
+        _user_id: &'a UserId,
+    ) -> BoxFuture<'a, Result<Option<FollowCounts>>> {
+        Box::pin(async { Ok(None) })
+    }
+    /// get the repositories a user starred after some time, or only
+    /// the most recent ones when there's no time, sorted by date.
+    /// Forges which don't tell when stars were given fail.
+    fn get_starred_repos<'a>(
+        &'a self,
+        user_id: &'a UserId,
+        _since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<StarredRepo>>> {
+        Box::pin(async move {
+            bail!(
+                "{} doesn't tell when the stars of {} were given",
+                self.host(),
+                user_id
+            )
+        })
+    }
+    /// the state of the API budget, for forges having one
+    fn budget_report(&self) -> Option<BudgetReport> {
+        None
+    }
+}
+
+/// build a client for the host with the given name (or for
+/// github.com when there's no name), according to its forge,
+/// unless fixtures are configured
+pub fn forge_client(
+    conf: &Conf,
+    host_name: Option<&str>,
+) -> Result<Arc<dyn Forge>> {
+    let host = conf.host(host_name)?;
+    if let Some(dir) = fixtures_dir(conf) {
+        return Ok(Arc::new(FixtureClient::new(conf, host, dir)?));
+    }
+    Ok(match host.forge {
+        ForgeKind::Github => Arc::new(GithubClient::new(conf, host)?),
+        ForgeKind::Gitlab => Arc::new(GitlabClient::new(conf, host)?),
+        ForgeKind::Gitea => Arc::new(GiteaClient::new(conf, host)?),
+    })
+}
+
+/// group per owner the observations of some repositories, each one
+/// being either observed or confirmed missing by the forge (not found).
+/// Any other failure must fail the whole call instead, as an owner whose
+/// repositories are all missing gets an empty UserObs, so that their
+/// disappearance is noticed
+pub(crate) fn owner_obss(
+    repo_obss: Vec<(&RepoId, Option<RepoObs>)>,
+    now: DateTime<Utc>,
+) -> Vec<UserObs> {
+    let mut user_obss: Vec<UserObs> = Vec::new();
+    for (repo_id, repo_obs) in repo_obss {
+        let idx = match user_obss.iter().position(|uo| uo.user_id == repo_id.owner) {
+            Some(idx) => idx,
+            None => {
+                user_obss.push(UserObs {
+                    user_id: repo_id.owner.clone(),
+                    time: now,
+                    counts: Vec::new(),
+                });
+                user_obss.len() - 1
+            }
+        };
+        user_obss[idx].counts.extend(repo_obs);
+    }
+    user_obss
+}
+
+/// remove from a RepoObs the metrics which weren't asked for
+pub(crate) fn retain_metrics(
+    mut repo_obs: RepoObs,
+    metrics: &[Metric],
+) -> RepoObs {
+    for metric in Metric::ALL {
+        if metric != Metric::Stars && !metrics.contains(&metric) {
+            repo_obs.set(metric, None);
+        }
+    }
+    repo_obs
+}
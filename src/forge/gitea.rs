@@ -0,0 +1,269 @@
+use {
+    crate::*,
+    anyhow::{
+        anyhow,
+        Result,
+    },
+    chrono::{
+        DateTime,
+        Utc,
+    },
+    futures::future::BoxFuture,
+    serde::Deserialize,
+    std::collections::HashMap,
+};
+
+/// the default max page size of the Gitea API
+const PAGE_SIZE: usize = 50;
+
+#[derive(Debug, Deserialize)]
+struct GTUser {
+    pub login: String,
+    pub full_name: Option<String>,
+    pub followers_count: Option<usize>,
+    pub following_count: Option<usize>,
+}
+
+impl GTUser {
+    fn follow_counts(&self) -> Option<FollowCounts> {
+        Some(FollowCounts {
+            followers: self.followers_count?,
+            following: self.following_count?,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GTOrg {
+    pub username: String,
+    pub full_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GTOwner {
+    pub login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GTRepo {
+    pub id: u64,
+    pub name: String,
+    pub owner: GTOwner,
+    pub fork: bool,
+    pub stars_count: usize,
+    pub forks_count: Option<usize>,
+    pub watchers_count: Option<usize>,
+    pub open_issues_count: Option<usize>,
+    pub open_pr_counter: Option<usize>,
+    pub archived: Option<bool>,
+}
+
+impl From<GTRepo> for RepoObs {
+    fn from(repo: GTRepo) -> Self {
+        Self {
+            repo_name: repo.name,
+            id: Some(repo.id.to_string()),
+            stars: repo.stars_count,
+            forks: repo.forks_count,
+            watchers: repo.watchers_count,
+            issues: repo.open_issues_count,
+            prs: repo.open_pr_counter,
+            archived: repo.archived,
+            ..Default::default()
+        }
+    }
+}
+
+/// a client for the REST API of a Gitea or Forgejo instance
+pub struct GiteaClient {
+    host: Host,
+    rest_client: RestClient,
+    /// the metrics to fetch in addition to stars
+    metrics: Vec<Metric>,
+}
+
+impl GiteaClient {
+    pub fn new(
+        conf: &Conf,
+        host: Host,
+    ) -> Result<Self> {
+        let authorization = host.token.as_ref().map(|token| format!("token {}", token));
+        let rest_client = RestClient::new(&host.api_url, authorization, "limit", PAGE_SIZE)?;
+        let metrics = conf.metrics()?;
+        Ok(Self {
+            host,
+            rest_client,
+            metrics,
+        })
+    }
+    /// get the non fork repositories of a user or organization
+    async fn get_repos(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<GTRepo>> {
+        let path = match user_id.kind {
+            OwnerKind::User => format!("/users/{}/repos", user_id.login),
+            OwnerKind::Organization => format!("/orgs/{}/repos", user_id.login),
+        };
+        let repos: Vec<GTRepo> = self
+            .rest_client
+            .get_all(&path)
+            .await?
+            .ok_or_else(|| anyhow!("No repositories found for {}", user_id))?;
+        Ok(repos
+            .into_iter()
+            .filter(|repo| !repo.fork && repo.owner.login.eq_ignore_ascii_case(&user_id.login))
+            .collect())
+    }
+    async fn get_repo(
+        &self,
+        repo_id: &RepoId,
+    ) -> Result<Option<GTRepo>> {
+        self.rest_client
+            .get(&format!("/repos/{}/{}", repo_id.owner.login, repo_id.name))
+            .await
+    }
+}
+
+impl Forge for GiteaClient {
+    fn host(&self) -> &Host {
+        &self.host
+    }
+    fn get_owner_kind<'a>(
+        &'a self,
+        login: &'a str,
+    ) -> BoxFuture<'a, Result<OwnerKind>> {
+        Box::pin(async move {
+            let org: Option<GTOrg> = self.rest_client.get(&format!("/orgs/{}", login)).await?;
+            if org.is_some() {
+                return Ok(OwnerKind::Organization);
+            }
+            let user: Option<GTUser> = self.rest_client.get(&format!("/users/{}", login)).await?;
+            match user {
+                Some(_) => Ok(OwnerKind::User),
+                None => Err(anyhow!(
+                    "No user or organization found with login {:?}",
+                    login
+                )),
+            }
+        })
+    }
+    fn get_user(
+        &self,
+        user_id: UserId,
+    ) -> BoxFuture<'_, Result<User>> {
+        Box::pin(async move {
+            let names = match user_id.kind {
+                OwnerKind::User => {
+                    let user: Option<GTUser> = self
+                        .rest_client
+                        .get(&format!("/users/{}", user_id.login))
+                        .await?;
+                    user.map(|u| {
+                        let follow_counts = u.follow_counts();
+                        (u.login, u.full_name, follow_counts)
+                    })
+                }
+                OwnerKind::Organization => {
+                    let org: Option<GTOrg> = self
+                        .rest_client
+                        .get(&format!("/orgs/{}", user_id.login))
+                        .await?;
+                    org.map(|o| (o.username, o.full_name, None))
+                }
+            };
+            let (login, full_name, follow_counts) =
+                names.ok_or_else(|| anyhow!("{} not found", user_id))?;
+            let non_fork_repositories_count = self.get_repos(&user_id).await?.len();
+            Ok(User {
+                name: full_name.filter(|name| !name.is_empty()).unwrap_or(login),
+                user_id,
+                non_fork_repositories_count,
+                follow_counts,
+            })
+        })
+    }
+    fn get_user_star_counts(
+        &self,
+        user_id: UserId,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<UserObs>> {
+        Box::pin(async move {
+            let counts = self
+                .get_repos(&user_id)
+                .await?
+                .into_iter()
+                .map(|repo| retain_metrics(repo.into(), &self.metrics))
+                .collect();
+            Ok(UserObs {
+                user_id,
+                time: now,
+                counts,
+            })
+        })
+    }
+    fn get_repo_stars<'a>(
+        &'a self,
+        repo_id: &'a RepoId,
+    ) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move {
+            self.get_repo(repo_id)
+                .await?
+                .map(|repo| repo.stars_count)
+                .ok_or_else(|| anyhow!("Repository {} not found", repo_id))
+        })
+    }
+    fn get_repos_star_counts<'a>(
+        &'a self,
+        repo_ids: &'a [RepoId],
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<UserObs>>> {
+        Box::pin(async move {
+            let mut repo_obss = Vec::new();
+            for repo_id in repo_ids {
+                let repo_obs = match self.get_repo(repo_id).await? {
+                    Some(repo) => Some(retain_metrics(repo.into(), &self.metrics)),
+                    None => {
+                        eprintln!("Repository {} not found", repo_id);
+                        None
+                    }
+                };
+                repo_obss.push((repo_id, repo_obs));
+            }
+            Ok(owner_obss(repo_obss, now))
+        })
+    }
+    fn get_follow_counts<'a>(
+        &'a self,
+        user_id: &'a UserId,
+    ) -> BoxFuture<'a, Result<Option<FollowCounts>>> {
+        Box::pin(async move {
+            let user: Option<GTUser> = self
+                .rest_client
+                .get(&format!("/users/{}", user_id.login))
+                .await?;
+            let user = user.ok_or_else(|| anyhow!("{} not found", user_id))?;
+            Ok(user.follow_counts())
+        })
+    }
+    fn locate_repos<'a>(
+        &'a self,
+        ids: &'a [String],
+    ) -> BoxFuture<'a, Result<HashMap<String, (RepoId, usize)>>> {
+        Box::pin(async move {
+            let mut located = HashMap::new();
+            for id in ids {
+                let repo: Option<GTRepo> = self
+                    .rest_client
+                    .get(&format!("/repositories/{}", id))
+                    .await?;
+                if let Some(repo) = repo {
+                    let owner = UserId::new(repo.owner.login).on_host(self.host.name.clone());
+                    let repo_id = RepoId::new(owner, repo.name);
+                    located.insert(id.clone(), (repo_id, repo.stars_count));
+                }
+            }
+            Ok(located)
+        })
+    }
+}
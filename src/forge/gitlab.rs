@@ -0,0 +1,278 @@
+use {
+    crate::*,
+    anyhow::{
+        anyhow,
+        Result,
+    },
+    chrono::{
+        DateTime,
+        Utc,
+    },
+    futures::future::BoxFuture,
+    serde::Deserialize,
+    std::collections::HashMap,
+};
+
+/// the max page size of the GitLab API
+const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct GLUser {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GLGroup {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GLNamespace {
+    pub full_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GLProject {
+    pub id: u64,
+    pub path: String,
+    pub namespace: GLNamespace,
+    pub star_count: usize,
+    pub forks_count: Option<usize>,
+    pub open_issues_count: Option<usize>,
+    pub archived: Option<bool>,
+    pub created_at: Option<DateTime<Utc>>,
+    /// present only for forks
+    pub forked_from_project: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GLStarrer {
+    pub starred_since: DateTime<Utc>,
+    pub user: GLStarrerUser,
+}
+
+#[derive(Debug, Deserialize)]
+struct GLStarrerUser {
+    pub username: String,
+}
+
+impl From<GLProject> for RepoObs {
+    fn from(project: GLProject) -> Self {
+        Self {
+            repo_name: project.path,
+            id: Some(project.id.to_string()),
+            stars: project.star_count,
+            forks: project.forks_count,
+            issues: project.open_issues_count,
+            archived: project.archived,
+            ..Default::default()
+        }
+    }
+}
+
+/// a client for the REST API of a GitLab instance, where
+/// organizations are groups and repositories are projects
+pub struct GitlabClient {
+    host: Host,
+    rest_client: RestClient,
+    /// the metrics to fetch in addition to stars
+    metrics: Vec<Metric>,
+}
+
+impl GitlabClient {
+    pub fn new(
+        conf: &Conf,
+        host: Host,
+    ) -> Result<Self> {
+        let authorization = host.token.as_ref().map(|token| format!("Bearer {}", token));
+        let rest_client = RestClient::new(&host.api_url, authorization, "per_page", PAGE_SIZE)?;
+        let metrics = conf.metrics()?;
+        Ok(Self {
+            host,
+            rest_client,
+            metrics,
+        })
+    }
+    async fn find_user(
+        &self,
+        login: &str,
+    ) -> Result<Option<GLUser>> {
+        let users: Option<Vec<GLUser>> = self
+            .rest_client
+            .get(&format!("/users?username={}", login))
+            .await?;
+        Ok(users.and_then(|users| users.into_iter().next()))
+    }
+    async fn find_group(
+        &self,
+        login: &str,
+    ) -> Result<Option<GLGroup>> {
+        self.rest_client
+            .get(&format!("/groups/{}", encode_path(login)))
+            .await
+    }
+    /// get the non fork projects of a user or group
+    async fn get_projects(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<GLProject>> {
+        let path = match user_id.kind {
+            OwnerKind::User => format!("/users/{}/projects", encode_path(&user_id.login)),
+            OwnerKind::Organization => format!("/groups/{}/projects", encode_path(&user_id.login)),
+        };
+        let projects: Vec<GLProject> = self
+            .rest_client
+            .get_all(&path)
+            .await?
+            .ok_or_else(|| anyhow!("No projects found for {}", user_id))?;
+        Ok(projects
+            .into_iter()
+            .filter(|project| project.forked_from_project.is_none())
+            .collect())
+    }
+    async fn get_project(
+        &self,
+        repo_id: &RepoId,
+    ) -> Result<Option<GLProject>> {
+        let path = format!("{}/{}", repo_id.owner.login, repo_id.name);
+        self.rest_client
+            .get(&format!("/projects/{}", encode_path(&path)))
+            .await
+    }
+}
+
+impl Forge for GitlabClient {
+    fn host(&self) -> &Host {
+        &self.host
+    }
+    fn get_owner_kind<'a>(
+        &'a self,
+        login: &'a str,
+    ) -> BoxFuture<'a, Result<OwnerKind>> {
+        Box::pin(async move {
+            if self.find_user(login).await?.is_some() {
+                Ok(OwnerKind::User)
+            } else if self.find_group(login).await?.is_some() {
+                Ok(OwnerKind::Organization)
+            } else {
+                Err(anyhow!("No user or group found with login {:?}", login))
+            }
+        })
+    }
+    fn get_user(
+        &self,
+        user_id: UserId,
+    ) -> BoxFuture<'_, Result<User>> {
+        Box::pin(async move {
+            let name = match user_id.kind {
+                OwnerKind::User => self.find_user(&user_id.login).await?.map(|u| u.name),
+                OwnerKind::Organization => self.find_group(&user_id.login).await?.map(|g| g.name),
+            };
+            let name = name.ok_or_else(|| anyhow!("{} not found", user_id))?;
+            let non_fork_repositories_count = self.get_projects(&user_id).await?.len();
+            Ok(User {
+                user_id,
+                name,
+                non_fork_repositories_count,
+                follow_counts: None,
+            })
+        })
+    }
+    fn get_user_star_counts(
+        &self,
+        user_id: UserId,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<UserObs>> {
+        Box::pin(async move {
+            let counts = self
+                .get_projects(&user_id)
+                .await?
+                .into_iter()
+                .map(|project| retain_metrics(project.into(), &self.metrics))
+                .collect();
+            Ok(UserObs {
+                user_id,
+                time: now,
+                counts,
+            })
+        })
+    }
+    fn get_repo_stars<'a>(
+        &'a self,
+        repo_id: &'a RepoId,
+    ) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move {
+            self.get_project(repo_id)
+                .await?
+                .map(|project| project.star_count)
+                .ok_or_else(|| anyhow!("Repository {} not found", repo_id))
+        })
+    }
+    fn get_repos_star_counts<'a>(
+        &'a self,
+        repo_ids: &'a [RepoId],
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<UserObs>>> {
+        Box::pin(async move {
+            let mut repo_obss = Vec::new();
+            for repo_id in repo_ids {
+                let repo_obs = match self.get_project(repo_id).await? {
+                    Some(project) => Some(retain_metrics(project.into(), &self.metrics)),
+                    None => {
+                        eprintln!("Repository {} not found", repo_id);
+                        None
+                    }
+                };
+                repo_obss.push((repo_id, repo_obs));
+            }
+            Ok(owner_obss(repo_obss, now))
+        })
+    }
+    fn get_star_history<'a>(
+        &'a self,
+        repo_id: &'a RepoId,
+    ) -> BoxFuture<'a, Result<StarHistory>> {
+        Box::pin(async move {
+            let project = self
+                .get_project(repo_id)
+                .await?
+                .ok_or_else(|| anyhow!("Repository {} not found", repo_id))?;
+            let starrers: Vec<GLStarrer> = self
+                .rest_client
+                .get_all(&format!("/projects/{}/starrers", project.id))
+                .await?
+                .unwrap_or_default();
+            let mut stars: Vec<Star> = starrers
+                .into_iter()
+                .map(|starrer| Star {
+                    login: starrer.user.username,
+                    starred_at: starrer.starred_since,
+                })
+                .collect();
+            stars.sort_by_key(|star| star.starred_at);
+            Ok(StarHistory {
+                created_at: project.created_at,
+                stars,
+            })
+        })
+    }
+    fn locate_repos<'a>(
+        &'a self,
+        ids: &'a [String],
+    ) -> BoxFuture<'a, Result<HashMap<String, (RepoId, usize)>>> {
+        Box::pin(async move {
+            let mut located = HashMap::new();
+            for id in ids {
+                let project: Option<GLProject> =
+                    self.rest_client.get(&format!("/projects/{}", id)).await?;
+                if let Some(project) = project {
+                    let owner =
+                        UserId::new(project.namespace.full_path).on_host(self.host.name.clone());
+                    let repo_id = RepoId::new(owner, project.path);
+                    located.insert(id.clone(), (repo_id, project.star_count));
+                }
+            }
+            Ok(located)
+        })
+    }
+}
@@ -0,0 +1,19 @@
+mod fixture;
+mod forge_kind;
+mod forge_trait;
+mod gitea;
+mod gitlab;
+mod rest_client;
+mod star_history;
+mod starred;
+
+pub use {
+    fixture::*,
+    forge_kind::*,
+    forge_trait::*,
+    gitea::*,
+    gitlab::*,
+    rest_client::*,
+    star_history::*,
+    starred::*,
+};
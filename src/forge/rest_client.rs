@@ -0,0 +1,142 @@
+use {
+    anyhow::Result,
+    reqwest::{
+        header,
+        StatusCode,
+    },
+    serde::de::DeserializeOwned,
+};
+
+/// a minimal client for the REST APIs of GitLab and Gitea
+pub struct RestClient {
+    http: reqwest::Client,
+    api_url: String,
+    /// the value of the Authorization header, if any
+    authorization: Option<String>,
+    /// the name of the query parameter setting the page size
+    page_size_param: &'static str,
+    page_size: usize,
+}
+
+impl RestClient {
+    pub fn new(
+        api_url: &str,
+        authorization: Option<String>,
+        page_size_param: &'static str,
+        page_size: usize,
+    ) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("starry/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            http,
+            api_url: api_url.trim_end_matches('/').to_string(),
+            authorization,
+            page_size_param,
+            page_size,
+        })
+    }
+    /// send a GET request to an URL, returning None on a 404
+    async fn send(
+        &self,
+        url: &str,
+    ) -> Result<Option<reqwest::Response>> {
+        let mut request = self.http.get(url);
+        if let Some(authorization) = &self.authorization {
+            request = request.header(header::AUTHORIZATION, authorization);
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?))
+    }
+    /// get a resource, or None if it's not found
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<Option<T>> {
+        match self.send(&format!("{}{}", self.api_url, path)).await? {
+            Some(response) => Ok(Some(response.json().await?)),
+            None => Ok(None),
+        }
+    }
+    /// get all the items of a paginated list, or None if the
+    /// list is not found.
+    ///
+    /// Servers may return shorter pages than asked, so the end of
+    /// the list is known from the `Link` header (GitLab and Gitea
+    /// give the URL of the next page), or from the `X-Total-Count`
+    /// header, or else from an empty page.
+    pub async fn get_all<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<Option<Vec<T>>> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let page_url = |page: usize| {
+            format!(
+                "{}{}{}{}={}&page={}",
+                self.api_url, path, separator, self.page_size_param, self.page_size, page,
+            )
+        };
+        let mut items = Vec::new();
+        let mut page = 1;
+        let mut url = page_url(page);
+        // whether the server gives links to the pages
+        let mut links = false;
+        loop {
+            let Some(response) = self.send(&url).await? else {
+                return Ok(None);
+            };
+            links |= response.headers().contains_key(header::LINK);
+            let next = next_link(response.headers());
+            let total: Option<usize> = response
+                .headers()
+                .get("x-total-count")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok());
+            let mut page_items: Vec<T> = response.json().await?;
+            if page_items.is_empty() {
+                break;
+            }
+            items.append(&mut page_items);
+            page += 1;
+            url = match next {
+                Some(next) => next,
+                // there are links, but none to a next page
+                None if links => break,
+                None if total.is_some_and(|total| items.len() >= total) => break,
+                None => page_url(page),
+            };
+        }
+        Ok(Some(items))
+    }
+}
+
+/// read the URL of the next page in a `Link` header
+/// (eg `<https://host/api?page=3>; rel="next", <...>; rel="last"`)
+fn next_link(headers: &header::HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|link| {
+            let (url, params) = link.split_once(';')?;
+            params
+                .split(';')
+                .any(|param| matches!(param.trim(), r#"rel="next""# | "rel=next"))
+                .then(|| {
+                    url.trim()
+                        .trim_start_matches('<')
+                        .trim_end_matches('>')
+                        .to_string()
+                })
+        })
+}
+
+/// encode a path (eg `group/subgroup`) so that it can be
+/// used as a single segment of an URL
+pub fn encode_path(path: &str) -> String {
+    path.replace('%', "%25").replace('/', "%2F")
+}
@@ -0,0 +1,34 @@
+use chrono::{
+    DateTime,
+    Utc,
+};
+
+/// a star currently given to a repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct Star {
+    /// the login of the stargazer
+    pub login: String,
+    pub starred_at: DateTime<Utc>,
+}
+
+/// the current stars of a repository, with when they were given.
+///
+/// Removed stars aren't known: the past numbers of stars computed
+/// from a star history are only approximate.
+#[derive(Debug, Clone, Default)]
+pub struct StarHistory {
+    /// when the repository was created, if known
+    pub created_at: Option<DateTime<Utc>>,
+    /// the stars, sorted by date
+    pub stars: Vec<Star>,
+}
+
+impl StarHistory {
+    /// the number of current stars given at or before some time
+    pub fn stars_at(
+        &self,
+        time: DateTime<Utc>,
+    ) -> usize {
+        self.stars.partition_point(|star| star.starred_at <= time)
+    }
+}
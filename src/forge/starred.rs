@@ -0,0 +1,68 @@
+use {
+    crate::*,
+    chrono::{
+        DateTime,
+        SecondsFormat,
+        Utc,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+};
+
+/// a repository starred by a user
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct StarredRepo {
+    pub starred_at: DateTime<Utc>,
+    /// the repository, as `owner/name`, on the host of the user
+    pub repo: String,
+    /// the number of stars of the repository when the star was noticed
+    pub stars: usize,
+}
+
+/// a repository starred by a user whose starred feed is followed
+#[derive(Debug, Clone, PartialEq)]
+pub struct StarredFeedItem {
+    pub user_id: UserId,
+    pub starred: StarredRepo,
+}
+
+impl StarredFeedItem {
+    /// the id of the starred repository, on the host of the user
+    pub fn repo_id(&self) -> Option<RepoId> {
+        let mut repo_id = RepoId::parse(&self.starred.repo)?;
+        repo_id.owner.host = self.user_id.host.clone();
+        Some(repo_id)
+    }
+    pub fn url(
+        &self,
+        conf: &Conf,
+    ) -> Option<String> {
+        let repo_id = self.repo_id()?;
+        let host = conf.host(self.user_id.host.as_deref()).ok()?;
+        Some(host.repo_url(&repo_id))
+    }
+}
+
+/// the table of starred repositories, for the structured outputs
+pub fn starred_table(
+    items: &[StarredFeedItem],
+    conf: &Conf,
+) -> Table {
+    let columns = ["user", "repo", "starred_at", "stars", "url"];
+    let mut table = Table::new(columns.iter().map(|c| c.to_string()).collect());
+    for item in items {
+        table.add_row(vec![
+            item.user_id.to_string().into(),
+            item.starred.repo.clone().into(),
+            item.starred
+                .starred_at
+                .to_rfc3339_opts(SecondsFormat::Secs, true)
+                .into(),
+            item.starred.stars.into(),
+            item.url(conf).into(),
+        ]);
+    }
+    table
+}
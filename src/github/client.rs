@@ -1,37 +1,432 @@
 use {
     crate::*,
-    anyhow::*,
+    anyhow::{
+        anyhow,
+        bail,
+        Context,
+        Result,
+    },
     byo_graphql::{
         Count,
-        GraphqlClient,
         List,
+        PageInfo,
     },
     chrono::{
         DateTime,
+        Local,
         Utc,
     },
-    serde::Deserialize,
+    cli_log::*,
+    futures::future::BoxFuture,
+    reqwest::{
+        header::{
+            HeaderMap,
+            RETRY_AFTER,
+        },
+        StatusCode,
+    },
+    serde::{
+        de::DeserializeOwned,
+        Deserialize,
+    },
+    serde_json::{
+        json,
+        Map,
+        Value,
+    },
+    std::{
+        collections::HashMap,
+        time::Duration,
+    },
 };
 
 pub static GITHUB_API_TOKEN_KEY: &str = "github-api-token";
 
+/// max number of repositories queried in one aliased request
+const REPOS_BATCH_SIZE: usize = 50;
+
+/// max number of tries of a query failing with transient errors
+const MAX_ATTEMPTS: usize = 5;
+
+/// delay before the first retry, doubled at each new try
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// longest delay asked by GitHub (with a `Retry-After` header)
+/// we accept to wait before retrying
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// a graphql response, before extraction of the rate limit
+#[derive(Debug, Deserialize)]
+struct GQRawResponse {
+    data: Option<Map<String, Value>>,
+    errors: Option<Vec<GQError>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GQError {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    message: Option<String>,
+    path: Option<Vec<Value>>,
+}
+
+impl GQError {
+    /// the alias of the top level field which wasn't found,
+    /// if that's what the error is about
+    fn not_found_alias(&self) -> Option<&str> {
+        if self.kind.as_deref() != Some("NOT_FOUND") {
+            return None;
+        }
+        self.path.as_ref()?.first()?.as_str()
+    }
+}
+
+/// a graphql response whose data may be partial when there are errors
+#[derive(Debug)]
+struct GQResponse<D> {
+    data: Option<D>,
+    errors: Vec<GQError>,
+}
+
+impl<D> GQResponse<D> {
+    fn error(&self) -> anyhow::Error {
+        let messages: Vec<&str> = self
+            .errors
+            .iter()
+            .filter_map(|e| e.message.as_deref())
+            .collect();
+        anyhow!("GitHub returned an error: {}", messages.join(", "))
+    }
+    /// the data, provided there was no error
+    fn data(self) -> Result<D> {
+        if !self.errors.is_empty() {
+            return Err(self.error());
+        }
+        self.data
+            .ok_or_else(|| anyhow!("no data in GitHub response"))
+    }
+    /// the data, which may lack some top level fields, with the
+    /// aliases of the fields GitHub didn't find. Any other error
+    /// fails the whole response
+    fn partial_data(self) -> Result<(D, Vec<String>)> {
+        let mut not_found = Vec::new();
+        for error in &self.errors {
+            match error.not_found_alias() {
+                Some(alias) => not_found.push(alias.to_string()),
+                None => return Err(self.error()),
+            }
+        }
+        let data = self
+            .data
+            .ok_or_else(|| anyhow!("no data in GitHub response"))?;
+        Ok((data, not_found))
+    }
+}
+
+/// a failed try of a query
+struct Failure {
+    error: anyhow::Error,
+    /// whether the query is worth retrying
+    transient: bool,
+    /// the delay GitHub asked to wait before retrying
+    retry_after: Option<Duration>,
+}
+
+impl From<reqwest::Error> for Failure {
+    fn from(e: reqwest::Error) -> Self {
+        Self {
+            transient: e.is_timeout() || e.is_connect(),
+            error: e.into(),
+            retry_after: None,
+        }
+    }
+}
+
+/// read the `Retry-After` header, when it's a number of seconds
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// tell whether a failed response is worth retrying the query: server
+/// hiccups and secondary rate limits, which GitHub signals with a 429,
+/// or with a 403 having a `Retry-After` header or telling it in its
+/// message. Other 403 are definitive (missing rights, blocked access, etc.)
+fn is_transient(
+    status: StatusCode,
+    retry_after: Option<Duration>,
+    message: &str,
+) -> bool {
+    if retry_after.is_some_and(|delay| delay > MAX_RETRY_AFTER) {
+        return false;
+    }
+    match status.as_u16() {
+        429 | 500 | 502 | 503 | 504 => true,
+        403 => retry_after.is_some() || message.to_lowercase().contains("secondary rate limit"),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(non_snake_case)]
+struct GQRepository {
+    pub id: String,
+    pub name: String,
+    pub isArchived: bool,
+    pub stargazers: Count,
+    pub forkCount: Option<usize>,
+    pub watchers: Option<Count>,
+    pub issues: Option<Count>,
+    pub pullRequests: Option<Count>,
+    pub releases: Option<GQReleases>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GQReleases {
+    pub nodes: Vec<GQRelease>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(non_snake_case)]
+struct GQRelease {
+    pub releaseAssets: GQReleaseAssets,
+}
+
+#[derive(Debug, Deserialize)]
+struct GQReleaseAssets {
+    pub nodes: Vec<GQReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(non_snake_case)]
+struct GQReleaseAsset {
+    pub downloadCount: usize,
+}
+
+impl From<GQRepository> for RepoObs {
+    fn from(repo: GQRepository) -> Self {
+        Self {
+            repo_name: repo.name,
+            id: Some(repo.id),
+            stars: repo.stargazers.into(),
+            forks: repo.forkCount,
+            watchers: repo.watchers.map(usize::from),
+            issues: repo.issues.map(usize::from),
+            prs: repo.pullRequests.map(usize::from),
+            downloads: repo.releases.map(|releases| {
+                releases
+                    .nodes
+                    .iter()
+                    .flat_map(|release| &release.releaseAssets.nodes)
+                    .map(|asset| asset.downloadCount)
+                    .sum()
+            }),
+            archived: Some(repo.isArchived),
+            reconstructed: None,
+        }
+    }
+}
+
+/// build the query part for a repository, with the stars
+/// and the other required metrics
+fn repository_query_body(metrics: &[Metric]) -> String {
+    let mut body = "{ id, name, isArchived, stargazers { totalCount }".to_string();
+    for metric in metrics {
+        body.push_str(match metric {
+            Metric::Stars => "",
+            Metric::Forks => ", forkCount",
+            Metric::Watchers => ", watchers { totalCount }",
+            Metric::Issues => ", issues(states: OPEN) { totalCount }",
+            Metric::Prs => ", pullRequests(states: OPEN) { totalCount }",
+            // only the assets of the most recent releases are counted
+            Metric::Downloads => {
+                ", releases(first: 10, orderBy: {field: CREATED_AT, direction: DESC}) \
+                { nodes { releaseAssets(first: 20) { nodes { downloadCount } } } }"
+            }
+        });
+    }
+    body.push_str(" }");
+    body
+}
+
 pub struct GithubClient {
-    gql_client: GraphqlClient,
+    host: Host,
+    http: reqwest::Client,
+    token: String,
+    /// the metrics to fetch in addition to stars
+    metrics: Vec<Metric>,
+    budget: RateBudget,
 }
 
 impl GithubClient {
-    pub fn new(conf: &Conf) -> Result<Self> {
-        let github_api_token = conf.get(GITHUB_API_TOKEN_KEY).ok_or_else(|| {
+    /// build a client for a GitHub host
+    pub fn new(
+        conf: &Conf,
+        host: Host,
+    ) -> Result<Self> {
+        let github_api_token = host.token.clone().ok_or_else(|| {
             anyhow!(
                 "You must first set a github API token with `starry set {} your-key`",
-                GITHUB_API_TOKEN_KEY
+                host_prop_key(GITHUB_API_TOKEN_KEY, host.name.as_deref()),
             )
         })?;
-        let mut gql_client = GraphqlClient::new("https://api.github.com/graphql")?;
-        gql_client.set_bearer_auth(github_api_token);
-        Ok(Self { gql_client })
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("starry/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        let metrics = conf.metrics()?;
+        Ok(Self {
+            host,
+            http,
+            token: github_api_token,
+            metrics,
+            budget: RateBudget::default(),
+        })
+    }
+    /// post a query once
+    async fn post(
+        &self,
+        query: &str,
+    ) -> std::result::Result<GQRawResponse, Failure> {
+        let response = self
+            .http
+            .post(&self.host.api_url)
+            .bearer_auth(&self.token)
+            .json(&json!({ "query": query }))
+            .send()
+            .await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response.json().await?);
+        }
+        let retry_after = retry_after(response.headers());
+        let body = response.text().await.unwrap_or_default();
+        // GitHub explains its refusals in a JSON message
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|value| value["message"].as_str().map(String::from))
+            .unwrap_or(body);
+        Err(Failure {
+            transient: is_transient(status, retry_after, &message),
+            error: anyhow!("GitHub answered {}: {}", status, message),
+            retry_after,
+        })
+    }
+    /// run a query, asking for its cost and the remaining budget
+    /// at the same time, and retrying on transient errors
+    async fn query<D: DeserializeOwned>(
+        &self,
+        query: &str,
+    ) -> Result<GQResponse<D>> {
+        if let Some(rate_limit) = self.budget.last() {
+            if rate_limit.remaining == 0 && rate_limit.resetAt > Utc::now() {
+                bail!(
+                    "GitHub API budget exhausted until {}",
+                    rate_limit.resetAt.with_timezone(&Local).format("%H:%M"),
+                );
+            }
+        }
+        // queries are all of the `{ ... }` form
+        let query = match query.rfind('}') {
+            Some(idx) => format!("{} {} }}", &query[..idx], RATE_LIMIT_QUERY),
+            None => bail!("invalid query: {:?}", query),
+        };
+        let mut delay = FIRST_RETRY_DELAY;
+        let mut attempt = 1;
+        let raw_response = loop {
+            let permit = self.budget.acquire().await;
+            let result = self.post(&query).await;
+            self.budget.release(permit);
+            match result {
+                Ok(raw_response) => break raw_response,
+                Err(failure) if attempt < MAX_ATTEMPTS && failure.transient => {
+                    warn!("attempt {} failed: {:?}", attempt, failure.error);
+                    tokio::time::sleep(failure.retry_after.unwrap_or(delay)).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(failure) => return Err(failure.error),
+            }
+        };
+        let errors = raw_response.errors.unwrap_or_default();
+        if errors
+            .iter()
+            .any(|e| e.kind.as_deref() == Some("RATE_LIMITED"))
+        {
+            bail!("GitHub API rate limit exceeded");
+        }
+        let data = match raw_response.data {
+            Some(mut map) => {
+                // GitHub Enterprise answers with a null rate limit
+                // when rate limiting is disabled
+                if let Some(rate_limit) = map.remove("rateLimit") {
+                    let rate_limit: Option<RateLimit> = serde_json::from_value(rate_limit)?;
+                    if let Some(rate_limit) = rate_limit {
+                        self.budget.record(rate_limit);
+                    }
+                }
+                Some(serde_json::from_value(Value::Object(map))?)
+            }
+            None => None,
+        };
+        Ok(GQResponse { data, errors })
+    }
+    /// run a query and return its first item, failing on any error
+    async fn get_first_item<Item: DeserializeOwned>(
+        &self,
+        query: &str,
+    ) -> Result<Item> {
+        let map: HashMap<String, Option<Item>> = self.query(query).await?.data()?;
+        map.into_values()
+            .next()
+            .flatten()
+            .ok_or_else(|| anyhow!("no data in GitHub response"))
+    }
+    /// run a query and return its first item, or none when GitHub
+    /// doesn't find it. Other errors (rate limits, network, auth,
+    /// malformed queries) are returned as errors.
+    async fn find_first_item<Item: DeserializeOwned>(
+        &self,
+        query: &str,
+    ) -> Result<Option<Item>> {
+        let response: GQResponse<HashMap<String, Option<Item>>> = self.query(query).await?;
+        let not_found = !response.errors.is_empty()
+            && response
+                .errors
+                .iter()
+                .all(|e| e.kind.as_deref() == Some("NOT_FOUND"));
+        if not_found {
+            return Ok(None);
+        }
+        Ok(response.data()?.into_values().next().flatten())
+    }
+    /// find whether a login is the one of a user or of an organization
+    pub async fn get_owner_kind(
+        &self,
+        login: &str,
+    ) -> Result<OwnerKind> {
+        #[derive(Deserialize)]
+        pub struct GQOwner {
+            pub __typename: String,
+        }
+        let query = format!(
+            r#"{{ repositoryOwner(login:"{}") {{ __typename }} }}"#,
+            login
+        );
+        let owner: GQOwner = self
+            .find_first_item(&query)
+            .await
+            .with_context(|| format!("reading the owner {:?}", login))?
+            .ok_or_else(|| anyhow!("No user or organization found with login {:?}", login))?;
+        OwnerKind::from_typename(&owner.__typename)
+            .ok_or_else(|| anyhow!("Unexpected owner type: {:?}", owner.__typename))
     }
-    /// get a GitHub user's information by its login
+    /// get a GitHub user's (or organization's) information by its login
     pub async fn get_user(
         &self,
         user_id: UserId,
@@ -39,39 +434,50 @@ impl GithubClient {
         // we extract into a dedicated structure matching the graphql response
         #[derive(Deserialize)]
         pub struct GQUser {
-            pub name: String,
+            pub name: Option<String>,
             pub repositories: Count,
+            // organizations have no followers
+            pub followers: Option<Count>,
+            pub following: Option<Count>,
         }
+        let follows = match user_id.kind {
+            OwnerKind::User => "followers { totalCount } following { totalCount }",
+            OwnerKind::Organization => "",
+        };
         let query = format!(
-            "{{ {} {{ name {}  }} }}",
+            "{{ {} {{ name {} {} }} }}",
             user_id.graphql_selector(),
             Count::query("repositories", "isFork: false"),
+            follows,
         );
-        let gquser: GQUser = self.gql_client.get_first_item(query).await?;
+        let gquser: GQUser = self.get_first_item(&query).await?;
+        let follow_counts = match (gquser.followers, gquser.following) {
+            (Some(followers), Some(following)) => Some(FollowCounts {
+                followers: followers.into(),
+                following: following.into(),
+            }),
+            _ => None,
+        };
         Ok(User {
+            name: gquser.name.unwrap_or_else(|| user_id.login.clone()),
             user_id,
-            name: gquser.name,
             non_fork_repositories_count: gquser.repositories.into(),
+            follow_counts,
         })
     }
     /// query the GitHub API to get a UserObs which has the number of stars
-    /// of all this user's repositories
+    /// of all this user's (or organization's) repositories
     pub async fn get_user_star_counts(
         &self,
         user_id: UserId,
         now: DateTime<Utc>,
     ) -> Result<UserObs> {
         #[derive(Deserialize)]
-        pub struct User {
+        pub struct Owner {
             pub repositories: Repositories,
         }
-        #[derive(Debug, Deserialize)]
-        pub struct Repository {
-            pub name: String,
-            pub stargazers: Count,
-        }
         let mut counts = Vec::new();
-        type Repositories = List<Repository>;
+        type Repositories = List<GQRepository>;
         // we'll do several requests if needed, using graphql pagination,
         // as the number of repositories of a user may exceed the tiny
         // capacity of a github graphql response
@@ -86,18 +492,13 @@ impl GithubClient {
                     page_size,
                     "isFork: false, ownerAffiliations: OWNER",
                 ),
-                Repositories::query_page_body("{ name, stargazers { totalCount } }"),
+                Repositories::query_page_body(&repository_query_body(&self.metrics)),
             );
-            // println!("query: {}", &query);
-            // println!("raw answer: {}", self.gql_client.text(&query)?);
-            let mut user: User = self.gql_client.get_first_item(&query).await?;
-            for repo in user.repositories.nodes.drain(..) {
-                counts.push(RepoObs {
-                    repo_name: repo.name,
-                    stars: repo.stargazers.into(),
-                });
+            let mut owner: Owner = self.get_first_item(&query).await?;
+            for repo in owner.repositories.nodes.drain(..) {
+                counts.push(repo.into());
             }
-            cursor = user.repositories.next_page_cursor();
+            cursor = owner.repositories.next_page_cursor();
             if cursor.is_none() {
                 break;
             }
@@ -108,4 +509,321 @@ impl GithubClient {
             counts,
         })
     }
+    /// get the current number of stars of a repository
+    pub async fn get_repo_stars(
+        &self,
+        repo_id: &RepoId,
+    ) -> Result<usize> {
+        #[derive(Deserialize)]
+        pub struct GQRepoStars {
+            pub stargazers: Count,
+        }
+        let query = format!(
+            "{{ {} {{ stargazers {{ totalCount }} }} }}",
+            repo_id.graphql_selector(),
+        );
+        let repo: GQRepoStars = self
+            .find_first_item(&query)
+            .await
+            .with_context(|| format!("reading the stars of {}", repo_id))?
+            .ok_or_else(|| anyhow!("Repository {} not found", repo_id))?;
+        Ok(repo.stargazers.into())
+    }
+    /// query the GitHub API to get the number of stars of some
+    /// repositories, grouped in one UserObs per owner.
+    ///
+    /// Repositories are fetched in batches, with one aliased
+    /// selector per repository in each query. Repositories which
+    /// GitHub doesn't find are reported and skipped, while any
+    /// other error fails the whole call.
+    pub async fn get_repos_star_counts(
+        &self,
+        repo_ids: &[RepoId],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<UserObs>> {
+        let mut repo_obss = Vec::new();
+        for batch in repo_ids.chunks(REPOS_BATCH_SIZE) {
+            let mut query = "{".to_string();
+            for (idx, repo_id) in batch.iter().enumerate() {
+                query.push_str(&format!(
+                    " r{}: {} {}",
+                    idx,
+                    repo_id.graphql_selector(),
+                    repository_query_body(&self.metrics),
+                ));
+            }
+            query.push_str(" }");
+            // GitHub answers with both data and errors when some
+            // repositories aren't found. Any other error fails the batch
+            let response: GQResponse<HashMap<String, Option<GQRepository>>> =
+                self.query(&query).await?;
+            let (mut data, not_found) = response.partial_data()?;
+            for (idx, repo_id) in batch.iter().enumerate() {
+                let alias = format!("r{}", idx);
+                let repo = match data.remove(&alias).flatten() {
+                    Some(repo) => repo,
+                    None if not_found.contains(&alias) => {
+                        eprintln!("Repository {} not found", repo_id);
+                        repo_obss.push((repo_id, None));
+                        continue;
+                    }
+                    None => bail!("No data for repository {} in GitHub response", repo_id),
+                };
+                repo_obss.push((repo_id, Some(RepoObs::from(repo))));
+            }
+        }
+        Ok(owner_obss(repo_obss, now))
+    }
+    /// get the current stars of a repository, with when they were
+    /// given, paging through its stargazers
+    pub async fn get_star_history(
+        &self,
+        repo_id: &RepoId,
+    ) -> Result<StarHistory> {
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        pub struct GQRepoStargazers {
+            pub createdAt: DateTime<Utc>,
+            pub stargazers: GQStargazers,
+        }
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        pub struct GQStargazers {
+            pub edges: Vec<GQStargazerEdge>,
+            pub pageInfo: PageInfo,
+        }
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        pub struct GQStargazerEdge {
+            pub starredAt: DateTime<Utc>,
+            pub node: GQStargazer,
+        }
+        #[derive(Deserialize)]
+        pub struct GQStargazer {
+            pub login: String,
+        }
+        let page_size = 100;
+        let mut history = StarHistory::default();
+        let mut cursor: Option<String> = None;
+        loop {
+            let query = format!(
+                "{{ {} {{ createdAt stargazers{} {{ edges {{ starredAt node {{ login }} }} \
+                pageInfo {{ endCursor hasNextPage }} }} }} }}",
+                repo_id.graphql_selector(),
+                List::<GQStargazerEdge>::query_page_selector(
+                    &cursor,
+                    page_size,
+                    "orderBy: {field: STARRED_AT, direction: ASC}",
+                ),
+            );
+            let repo: GQRepoStargazers = self
+                .find_first_item(&query)
+                .await
+                .with_context(|| format!("reading the stargazers of {}", repo_id))?
+                .ok_or_else(|| anyhow!("Repository {} not found", repo_id))?;
+            history.created_at = Some(repo.createdAt);
+            history
+                .stars
+                .extend(repo.stargazers.edges.into_iter().map(|edge| Star {
+                    login: edge.node.login,
+                    starred_at: edge.starredAt,
+                }));
+            let page_info = repo.stargazers.pageInfo;
+            cursor = match page_info.hasNextPage {
+                Some(true) => page_info.endCursor,
+                _ => None,
+            };
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(history)
+    }
+    /// get the repositories a user starred after some time, paging
+    /// from the most recent star, or only the first page when there's
+    /// no time
+    pub async fn get_starred_repos(
+        &self,
+        user_id: &UserId,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<StarredRepo>> {
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        pub struct GQUserStarred {
+            pub starredRepositories: GQStarredRepositories,
+        }
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        pub struct GQStarredRepositories {
+            pub edges: Vec<GQStarredEdge>,
+            pub pageInfo: PageInfo,
+        }
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        pub struct GQStarredEdge {
+            pub starredAt: DateTime<Utc>,
+            pub node: GQStarredRepo,
+        }
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        pub struct GQStarredRepo {
+            pub nameWithOwner: String,
+            pub stargazerCount: usize,
+        }
+        let page_size = 100;
+        let mut starred = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let query = format!(
+                "{{ {} {{ starredRepositories{} {{ edges {{ starredAt node {{ nameWithOwner stargazerCount }} }} \
+                pageInfo {{ endCursor hasNextPage }} }} }} }}",
+                user_id.graphql_selector(),
+                List::<GQStarredEdge>::query_page_selector(
+                    &cursor,
+                    page_size,
+                    "orderBy: {field: STARRED_AT, direction: DESC}",
+                ),
+            );
+            let user: GQUserStarred = self
+                .find_first_item(&query)
+                .await
+                .with_context(|| format!("reading the stars given by {}", user_id))?
+                .ok_or_else(|| anyhow!("User {} not found", user_id))?;
+            let mut reached_since = false;
+            for edge in user.starredRepositories.edges {
+                if since.is_some_and(|since| edge.starredAt <= since) {
+                    reached_since = true;
+                    break;
+                }
+                starred.push(StarredRepo {
+                    starred_at: edge.starredAt,
+                    repo: edge.node.nameWithOwner,
+                    stars: edge.node.stargazerCount,
+                });
+            }
+            let page_info = user.starredRepositories.pageInfo;
+            cursor = match page_info.hasNextPage {
+                Some(true) if since.is_some() && !reached_since => page_info.endCursor,
+                _ => None,
+            };
+            if cursor.is_none() {
+                break;
+            }
+        }
+        starred.reverse();
+        Ok(starred)
+    }
+    /// find where repositories are now, given their ids, and
+    /// how many stars they have. Repositories which can't be
+    /// seen anymore are absent from the returned map.
+    pub async fn locate_repos(
+        &self,
+        ids: &[String],
+    ) -> Result<HashMap<String, (RepoId, usize)>> {
+        #[derive(Deserialize)]
+        pub struct GQOwner {
+            pub login: String,
+        }
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        pub struct GQLocatedRepo {
+            pub id: String,
+            pub name: String,
+            pub owner: GQOwner,
+            pub stargazerCount: usize,
+        }
+        #[derive(Deserialize)]
+        struct Data {
+            nodes: Vec<Option<GQLocatedRepo>>,
+        }
+        let mut located = HashMap::new();
+        for batch in ids.chunks(REPOS_BATCH_SIZE) {
+            let ids = batch
+                .iter()
+                .map(|id| format!("{:?}", id))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let query = format!(
+                "{{ nodes(ids: [{}]) {{ ... on Repository {{ id, name, owner {{ login }}, stargazerCount }} }} }}",
+                ids,
+            );
+            // missing nodes come as errors, which is why we don't check them
+            let response: GQResponse<Data> = self.query(&query).await?;
+            for repo in response.data.into_iter().flat_map(|d| d.nodes).flatten() {
+                let owner = UserId::new(repo.owner.login).on_host(self.host.name.clone());
+                let repo_id = RepoId::new(owner, repo.name);
+                located.insert(repo.id, (repo_id, repo.stargazerCount));
+            }
+        }
+        Ok(located)
+    }
+}
+
+impl Forge for GithubClient {
+    fn host(&self) -> &Host {
+        &self.host
+    }
+    fn get_owner_kind<'a>(
+        &'a self,
+        login: &'a str,
+    ) -> BoxFuture<'a, Result<OwnerKind>> {
+        Box::pin(GithubClient::get_owner_kind(self, login))
+    }
+    fn get_user(
+        &self,
+        user_id: UserId,
+    ) -> BoxFuture<'_, Result<User>> {
+        Box::pin(GithubClient::get_user(self, user_id))
+    }
+    fn get_user_star_counts(
+        &self,
+        user_id: UserId,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<UserObs>> {
+        Box::pin(GithubClient::get_user_star_counts(self, user_id, now))
+    }
+    fn get_repo_stars<'a>(
+        &'a self,
+        repo_id: &'a RepoId,
+    ) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(GithubClient::get_repo_stars(self, repo_id))
+    }
+    fn get_repos_star_counts<'a>(
+        &'a self,
+        repo_ids: &'a [RepoId],
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<Vec<UserObs>>> {
+        Box::pin(GithubClient::get_repos_star_counts(self, repo_ids, now))
+    }
+    fn locate_repos<'a>(
+        &'a self,
+        ids: &'a [String],
+    ) -> BoxFuture<'a, Result<HashMap<String, (RepoId, usize)>>> {
+        Box::pin(GithubClient::locate_repos(self, ids))
+    }
+    fn get_star_history<'a>(
+        &'a self,
+        repo_id: &'a RepoId,
+    ) -> BoxFuture<'a, Result<StarHistory>> {
+        Box::pin(GithubClient::get_star_history(self, repo_id))
+    }
+    fn get_follow_counts<'a>(
+        &'a self,
+        user_id: &'a UserId,
+    ) -> BoxFuture<'a, Result<Option<FollowCounts>>> {
+        Box::pin(async move {
+            let user = GithubClient::get_user(self, user_id.clone()).await?;
+            Ok(user.follow_counts)
+        })
+    }
+    fn get_starred_repos<'a>(
+        &'a self,
+        user_id: &'a UserId,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<StarredRepo>>> {
+        Box::pin(GithubClient::get_starred_repos(self, user_id, since))
+    }
+    fn budget_report(&self) -> Option<BudgetReport> {
+        self.budget.report()
+    }
 }
@@ -1,10 +1,14 @@
 mod client;
+mod owner_kind;
+mod rate_limit;
 mod repo_id;
 mod user;
 mod user_id;
 
 pub use {
     client::*,
+    owner_kind::*,
+    rate_limit::*,
     repo_id::*,
     user::*,
     user_id::*,
@@ -0,0 +1,46 @@
+use {
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+    std::fmt,
+};
+
+/// the kind of a repository owner on GitHub
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OwnerKind {
+    #[default]
+    User,
+    Organization,
+}
+
+impl OwnerKind {
+    /// build from the `__typename` of a GitHub `RepositoryOwner`
+    pub fn from_typename(typename: &str) -> Option<Self> {
+        match typename {
+            "User" => Some(Self::User),
+            "Organization" => Some(Self::Organization),
+            _ => None,
+        }
+    }
+    /// the name of the root field to query an owner of this kind
+    pub fn graphql_field(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Organization => "organization",
+        }
+    }
+}
+
+impl fmt::Display for OwnerKind {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            Self::User => write!(f, "User"),
+            Self::Organization => write!(f, "Organization"),
+        }
+    }
+}
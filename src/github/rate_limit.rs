@@ -0,0 +1,165 @@
+use {
+    chrono::{
+        DateTime,
+        Local,
+        Utc,
+    },
+    serde::Deserialize,
+    std::{
+        fmt,
+        sync::Mutex,
+    },
+    tokio::sync::{
+        Semaphore,
+        SemaphorePermit,
+    },
+};
+
+/// max number of queries running at the same time
+pub const MAX_CONCURRENCY: usize = 50;
+
+/// the graphql field to add to a query to know its
+/// cost and the remaining budget
+pub const RATE_LIMIT_QUERY: &str = "rateLimit { cost remaining resetAt }";
+
+/// the state of the GitHub GraphQL API point budget,
+/// as returned with every query
+#[derive(Debug, Clone, Deserialize)]
+#[allow(non_snake_case)]
+pub struct RateLimit {
+    pub cost: usize,
+    pub remaining: usize,
+    pub resetAt: DateTime<Utc>,
+}
+
+/// the point budget shared by all queries of a client, which
+/// lowers the number of concurrent queries when it runs low
+#[derive(Debug)]
+pub struct RateBudget {
+    semaphore: Semaphore,
+    state: Mutex<BudgetState>,
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    /// current number of permits of the semaphore
+    concurrency: usize,
+    last: Option<RateLimit>,
+    spent: usize,
+}
+
+impl Default for RateBudget {
+    fn default() -> Self {
+        Self {
+            semaphore: Semaphore::new(MAX_CONCURRENCY),
+            state: Mutex::new(BudgetState {
+                concurrency: MAX_CONCURRENCY,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// the number of concurrent queries we allow for a remaining budget:
+/// one more query for every 100 points
+pub fn allowed_concurrency(remaining: usize) -> usize {
+    (remaining / 100).clamp(1, MAX_CONCURRENCY)
+}
+
+impl RateBudget {
+    /// wait for the right to run a query
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        // the semaphore is never closed
+        self.semaphore.acquire().await.unwrap()
+    }
+    /// give back the right to run a query, forgetting it
+    /// when the budget asks for less concurrency
+    pub fn release(
+        &self,
+        permit: SemaphorePermit<'_>,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let allowed = state
+            .last
+            .as_ref()
+            .map_or(MAX_CONCURRENCY, |rl| allowed_concurrency(rl.remaining));
+        if state.concurrency > allowed {
+            state.concurrency -= 1;
+            permit.forget();
+        }
+    }
+    /// take into account the rate limit info of a response
+    pub fn record(
+        &self,
+        rate_limit: RateLimit,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state.spent += rate_limit.cost;
+        // responses may come unordered: we keep the lowest remaining
+        // budget among the ones of the same period
+        let (keep, new_period) = match &state.last {
+            Some(last) if last.resetAt == rate_limit.resetAt => {
+                (rate_limit.remaining < last.remaining, false)
+            }
+            Some(last) => {
+                let new_period = rate_limit.resetAt > last.resetAt;
+                (new_period, new_period)
+            }
+            None => (true, false),
+        };
+        if new_period {
+            // the permits forgotten when the budget was low are restored
+            let allowed = allowed_concurrency(rate_limit.remaining);
+            if allowed > state.concurrency {
+                self.semaphore.add_permits(allowed - state.concurrency);
+                state.concurrency = allowed;
+            }
+        }
+        if keep {
+            state.last = Some(rate_limit);
+        }
+    }
+    /// the number of queries which may currently run at the same time
+    pub fn concurrency(&self) -> usize {
+        self.state.lock().unwrap().concurrency
+    }
+    /// the last known state of the budget
+    pub fn last(&self) -> Option<RateLimit> {
+        self.state.lock().unwrap().last.clone()
+    }
+    /// the number of points spent by this client's queries
+    pub fn spent(&self) -> usize {
+        self.state.lock().unwrap().spent
+    }
+    /// a printable report of the budget, if at least one
+    /// query returned its rate limit
+    pub fn report(&self) -> Option<BudgetReport> {
+        self.last().map(|last| BudgetReport {
+            spent: self.spent(),
+            remaining: last.remaining,
+            reset_at: last.resetAt,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct BudgetReport {
+    pub spent: usize,
+    pub remaining: usize,
+    pub reset_at: DateTime<Utc>,
+}
+
+impl fmt::Display for BudgetReport {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(
+            f,
+            "{} points spent, {} remaining until {}",
+            self.spent,
+            self.remaining,
+            self.reset_at.with_timezone(&Local).format("%H:%M"),
+        )
+    }
+}
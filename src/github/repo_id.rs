@@ -1,9 +1,10 @@
 use {
     crate::*,
+    anyhow::Result,
     std::fmt,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RepoId {
     pub owner: UserId,
     pub name: String,
@@ -19,10 +20,20 @@ impl RepoId {
             name: name.into(),
         }
     }
+    /// parse a `owner/name` string, where the owner may be qualified by its host
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut tokens = s.split('/');
+        match (tokens.next(), tokens.next(), tokens.next()) {
+            (Some(owner), Some(name), None) if !owner.is_empty() && !name.is_empty() => {
+                Some(Self::new(UserId::parse(owner), name))
+            }
+            _ => None,
+        }
+    }
     pub fn graphql_selector(&self) -> String {
         format!(
             r#"repository(owner:"{}", name:"{}")"#,
-            &self.owner, &self.name
+            &self.owner.login, &self.name
         )
     }
 }
@@ -35,3 +46,29 @@ impl fmt::Display for RepoId {
         write!(f, "{}/{}", self.owner, self.name)
     }
 }
+
+impl RepoId {
+    /// check a repository exists on its forge. Print its number of stars
+    /// if it's the case, print an error in other cases
+    pub async fn check_on_forge(
+        &self,
+        conf: &Conf,
+    ) -> Result<bool> {
+        let forge = forge_client(conf, self.owner.host.as_deref())?;
+        match forge.get_repo_stars(self).await {
+            Ok(stars) => {
+                println!(
+                    "Repository {} has {} stars on {}",
+                    self,
+                    stars,
+                    forge.host()
+                );
+                Ok(true)
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                Ok(false)
+            }
+        }
+    }
+}
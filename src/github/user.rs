@@ -1,8 +1,24 @@
-use crate::*;
+use {
+    crate::*,
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+};
 
 #[derive(Debug)]
 pub struct User {
     pub user_id: UserId,
     pub name: String,
     pub non_fork_repositories_count: usize,
+    /// the numbers of followers and followed users, when the
+    /// owner is a user and the forge tells them
+    pub follow_counts: Option<FollowCounts>,
+}
+
+/// the audience of a user
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct FollowCounts {
+    pub followers: usize,
+    pub following: usize,
 }
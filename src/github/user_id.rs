@@ -4,19 +4,51 @@ use {
     std::fmt,
 };
 
+/// the id of a repository owner, either a user or an organization
+///
+/// Owners which aren't on github.com are qualified with the name
+/// of their host, as in `login@host`.
 #[derive(Debug, Clone, PartialEq)]
 pub struct UserId {
     pub login: String,
+    pub kind: OwnerKind,
+    /// the name of the host, when not github.com
+    pub host: Option<String>,
 }
 
 impl UserId {
     pub fn new<S: Into<String>>(login: S) -> Self {
+        Self::with_kind(login, OwnerKind::User)
+    }
+    pub fn with_kind<S: Into<String>>(
+        login: S,
+        kind: OwnerKind,
+    ) -> Self {
         Self {
             login: login.into(),
+            kind,
+            host: None,
+        }
+    }
+    /// parse a `login` or `login@host` string
+    pub fn parse(s: &str) -> Self {
+        match s.split_once('@') {
+            Some((login, host)) if !host.is_empty() => Self {
+                host: Some(host.to_string()),
+                ..Self::new(login)
+            },
+            _ => Self::new(s),
         }
     }
+    pub fn on_host(
+        mut self,
+        host: Option<String>,
+    ) -> Self {
+        self.host = host;
+        self
+    }
     pub fn graphql_selector(&self) -> String {
-        format!(r#"user(login:"{}")"#, &self.login)
+        format!(r#"{}(login:"{}")"#, self.kind.graphql_field(), &self.login)
     }
 }
 
@@ -25,29 +57,50 @@ impl fmt::Display for UserId {
         &self,
         f: &mut fmt::Formatter<'_>,
     ) -> fmt::Result {
-        write!(f, "{}", self.login)
+        match &self.host {
+            Some(host) => write!(f, "{}@{}", self.login, host),
+            None => write!(f, "{}", self.login),
+        }
     }
 }
 
 impl UserId {
-    /// check a user exists on github. Print some basic information
-    /// if it's the case, print an error in other cases
-    pub async fn check_on_github(
-        self,
+    /// check an owner exists on its forge and find whether it's a user
+    /// or an organization. Print some basic information if it's
+    /// the case, print an error in other cases
+    pub async fn check_on_forge(
+        name: &str,
         conf: &Conf,
-    ) -> Result<bool> {
-        let github_client = GithubClient::new(conf)?;
-        match github_client.get_user(self).await {
+    ) -> Result<Option<Self>> {
+        let mut user_id = Self::parse(name);
+        let forge = forge_client(conf, user_id.host.as_deref())?;
+        user_id.kind = match forge.get_owner_kind(&user_id.login).await {
+            Ok(kind) => kind,
+            Err(e) => {
+                eprintln!("{}", e);
+                return Ok(None);
+            }
+        };
+        match forge.get_user(user_id.clone()).await {
             Ok(user) => {
                 println!(
-                    "User {} has {} non forked repositories on GitHub",
-                    user.name, user.non_fork_repositories_count,
+                    "{} {} has {} non forked repositories on {}",
+                    user_id.kind,
+                    user.name,
+                    user.non_fork_repositories_count,
+                    forge.host(),
                 );
-                Ok(true)
+                if let Some(follow_counts) = user.follow_counts {
+                    println!(
+                        "{} has {} followers and follows {} users",
+                        user.name, follow_counts.followers, follow_counts.following,
+                    );
+                }
+                Ok(Some(user_id))
             }
             Err(e) => {
                 eprintln!("{}", e);
-                Ok(false)
+                Ok(None)
             }
         }
     }
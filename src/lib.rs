@@ -2,8 +2,10 @@ mod app;
 mod cli;
 mod conf;
 mod database;
+mod forge;
 mod github;
 mod md;
+mod notify;
 mod progress;
 
 pub use {
@@ -11,7 +13,9 @@ pub use {
     cli::*,
     conf::*,
     database::*,
+    forge::*,
     github::*,
     md::*,
+    notify::*,
     progress::*,
 };
@@ -13,33 +13,47 @@ ${cropped
 ${kept-count} most significant ones:
 }
 |:-:|:-:|:-:|
-|**owner**|**name**|**last**|**trend**|**now**|**url** (ctrl-click to open)|
-|-:|:-|-:|:-:|-:|:-|
+|**owner**|**name**|**event**|**last**|**trend**|**now**|**url** (ctrl-click to open)|
+|-:|:-|:-|-:|:-:|-:|:-|
 ${changes
-|${owner}|**${name}**|${last}|${trend}|**${now}|${url}|
+|${owner}|**${name}**|${event}|${last}|${trend}|**${now}|${url}|
 }
-|-|-|-|-|-|-|
+|-|-|-|-|-|-|-|
 "#;
 
 pub struct ChangeReport<'c> {
     changes: &'c [RepoChange],
+    conf: &'c Conf,
     max_rows: usize,
 }
 
 impl<'c> ChangeReport<'c> {
     pub fn new(
         changes: &'c [RepoChange],
+        conf: &'c Conf,
         max_rows: usize,
     ) -> Self {
-        Self { changes, max_rows }
+        Self {
+            changes,
+            conf,
+            max_rows,
+        }
     }
     pub fn print(
         &self,
         skin: &MadSkin,
     ) {
+        let (width, _) = terminal_size();
+        print!("{}", self.render(skin, width as usize));
+    }
+    /// render the report as text fitting the given width
+    pub fn render(
+        &self,
+        skin: &MadSkin,
+        width: usize,
+    ) -> String {
         if self.changes.is_empty() {
-            println!("no change");
-            return;
+            return "no change\n".to_string();
         }
         let mut expander = OwningTemplateExpander::new();
         expander
@@ -50,22 +64,29 @@ impl<'c> ChangeReport<'c> {
                 .sub("changes")
                 .set("owner", &change.repo_id.owner)
                 .set("name", &change.repo_id.name)
+                .set_md(
+                    "event",
+                    change
+                        .event()
+                        .map_or("".to_string(), |e| format!("*{}*", e)),
+                )
                 .set(
                     "last",
                     change.old_stars.map_or("".to_string(), |s| s.to_string()),
                 )
                 //.set_md("trend", format!("{} {}", change.value(), change.trend_markdown()))
                 .set_md("trend", change.trend_markdown())
-                .set("now", change.new_stars)
-                .set("url", change.url());
+                .set(
+                    "now",
+                    change.new_stars.map_or("".to_string(), |s| s.to_string()),
+                )
+                .set("url", change.url(self.conf).unwrap_or_default());
         }
         if self.changes.len() > self.max_rows {
             expander.sub("cropped").set("kept-count", self.max_rows);
         }
         let template = TextTemplate::from(TEMPLATE);
         let text = expander.expand(&template);
-        let (width, _) = terminal_size();
-        let fmt_text = FmtText::from_text(skin, text, Some(width as usize));
-        print!("{}", fmt_text);
+        FmtText::from_text(skin, text, Some(width)).to_string()
     }
 }
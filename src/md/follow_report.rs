@@ -0,0 +1,85 @@
+use {
+    crate::*,
+    minimad::{
+        OwningTemplateExpander,
+        TextTemplate,
+    },
+    termimad::*,
+};
+
+static TEMPLATE: &str = r#"
+${change-count} changes in followers
+|:-:|:-:|:-:|
+|**who**|**followers**|**change**|**following**|**change**|
+|-:|-:|-:|-:|-:|
+${changes
+|${who}|**${followers}**|${followers-diff}|${following}|${following-diff}|
+}
+|-|-|-|-|-|
+"#;
+
+/// the section listing the changes in the numbers of
+/// followers and followed users of the followed users
+pub struct FollowReport<'c> {
+    changes: &'c [FollowChange],
+}
+
+/// the signed difference between two counts, empty when there's none
+fn diff(
+    old: usize,
+    new: usize,
+) -> String {
+    if new > old {
+        format!("+{}", new - old)
+    } else if new < old {
+        format!("-{}", old - new)
+    } else {
+        String::new()
+    }
+}
+
+impl<'c> FollowReport<'c> {
+    pub fn new(changes: &'c [FollowChange]) -> Self {
+        Self { changes }
+    }
+    pub fn print(
+        &self,
+        skin: &MadSkin,
+    ) {
+        let (width, _) = terminal_size();
+        print!("{}", self.render(skin, width as usize));
+    }
+    /// render the section as text fitting the given width, or
+    /// nothing when there's no change
+    pub fn render(
+        &self,
+        skin: &MadSkin,
+        width: usize,
+    ) -> String {
+        if self.changes.is_empty() {
+            return String::new();
+        }
+        let mut expander = OwningTemplateExpander::new();
+        expander
+            .set_default("")
+            .set("change-count", self.changes.len());
+        for change in self.changes {
+            expander
+                .sub("changes")
+                .set("who", &change.user_id)
+                .set("followers", change.new.followers)
+                .set(
+                    "followers-diff",
+                    diff(change.old.followers, change.new.followers),
+                )
+                .set("following", change.new.following)
+                .set(
+                    "following-diff",
+                    diff(change.old.following, change.new.following),
+                );
+        }
+        let template = TextTemplate::from(TEMPLATE);
+        let text = expander.expand(&template);
+        FmtText::from_text(skin, text, Some(width)).to_string()
+    }
+}
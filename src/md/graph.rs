@@ -0,0 +1,317 @@
+use {
+    crate::*,
+    chrono::{
+        DateTime,
+        Duration,
+        Local,
+        Utc,
+    },
+    crossterm::style::Color::{
+        self,
+        *,
+    },
+    std::fmt::Write,
+    termimad::*,
+};
+
+/// the colors of the series, in order
+static SERIES_COLORS: &[Color] = &[
+    AnsiValue(178),
+    AnsiValue(39),
+    AnsiValue(204),
+    Green,
+    AnsiValue(141),
+    Cyan,
+];
+
+/// the characters distinguishing the series when there's no color
+static SERIES_MARKERS: &[char] = &['•', '+', 'x', 'o', '*', '#'];
+
+/// the first braille character, without any dot
+const BRAILLE_BASE: u32 = 0x2800;
+
+/// the bits of the dots of a braille character, by [x][y]
+/// in the 2x4 grid of the character
+const BRAILLE_DOTS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// a line chart of the series of an extract, drawn with braille
+/// characters, with axis labels and a legend.
+///
+/// When there's no color and several series, every series is
+/// drawn with its own marker instead, one per character.
+///
+/// The x axis is dotted below the observations reconstructed
+/// by a backfill.
+pub struct Graph<'e> {
+    extract: &'e Extract,
+}
+
+/// a grid of braille dots for one series
+struct Canvas {
+    width: usize,
+    /// the dots of every character, row by row
+    cells: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(
+        width: usize,
+        height: usize,
+    ) -> Self {
+        Self {
+            width,
+            cells: vec![0; width * height],
+        }
+    }
+    fn set(
+        &mut self,
+        x: usize,
+        y: usize,
+    ) {
+        let idx = (y / 4) * self.width + x / 2;
+        if idx < self.cells.len() {
+            self.cells[idx] |= BRAILLE_DOTS[x % 2][y % 4];
+        }
+    }
+    /// draw a segment between two dots
+    fn line(
+        &mut self,
+        (x0, y0): (usize, usize),
+        (x1, y1): (usize, usize),
+    ) {
+        let (x0, y0, x1, y1) = (x0 as i64, y0 as i64, x1 as i64, y1 as i64);
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+        loop {
+            self.set(x as usize, y as usize);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+    fn dots(
+        &self,
+        col: usize,
+        row: usize,
+    ) -> u8 {
+        self.cells[row * self.width + col]
+    }
+}
+
+/// format a time for the x axis, with a precision depending
+/// on the duration of the graph
+fn time_label(
+    time: DateTime<Utc>,
+    span: Duration,
+) -> String {
+    let format = if span < Duration::days(2) {
+        "%m-%d %H:%M"
+    } else {
+        "%Y-%m-%d"
+    };
+    time.with_timezone(&Local).format(format).to_string()
+}
+
+impl<'e> Graph<'e> {
+    pub fn new(extract: &'e Extract) -> Self {
+        Self { extract }
+    }
+    pub fn print(
+        &self,
+        skin: &MadSkin,
+        color: bool,
+        height: usize,
+    ) {
+        let (width, _) = terminal_size();
+        print!("{}", self.render(skin, color, width as usize, height));
+    }
+    /// the points of a series, as (time, value)
+    fn points(
+        &self,
+        idx: usize,
+    ) -> Vec<(DateTime<Utc>, usize)> {
+        self.extract
+            .lines
+            .iter()
+            .filter_map(|line| line.counts[idx].map(|count| (line.time, count)))
+            .collect()
+    }
+    /// render the graph, with `height` lines for the plot itself
+    pub fn render(
+        &self,
+        skin: &MadSkin,
+        color: bool,
+        width: usize,
+        height: usize,
+    ) -> String {
+        let series: Vec<Vec<(DateTime<Utc>, usize)>> = (0..self.extract.names.len())
+            .map(|idx| self.points(idx))
+            .collect();
+        let mut times = series.iter().flatten().map(|&(time, _)| time);
+        let Some(first) = times.next() else {
+            return "nothing to draw\n".to_string();
+        };
+        let (start, end) = times.fold((first, first), |(s, e), t| (s.min(t), e.max(t)));
+        let values = || series.iter().flatten().map(|&(_, value)| value);
+        let min = values().min().unwrap_or(0);
+        let max = values().max().unwrap_or(0).max(min + 1);
+        let height = height.max(2);
+        // the first, middle and last lines are labelled with their value
+        let labels: Vec<String> = (0..height)
+            .map(|row| {
+                if row == 0 || row == height - 1 || (row == height / 2 && height > 2) {
+                    let ratio = row as f64 / (height - 1) as f64;
+                    let value = max as f64 - ratio * (max - min) as f64;
+                    (value.round() as usize).to_string()
+                } else {
+                    String::new()
+                }
+            })
+            .collect();
+        let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+        let plot_width = width.saturating_sub(label_width + 2).max(10);
+
+        // we draw every series on its own canvas
+        let (dots_x, dots_y) = (plot_width * 2 - 1, height * 4 - 1);
+        let span = end - start;
+        let to_dot = |(time, value): (DateTime<Utc>, usize)| {
+            let x = if span > Duration::zero() {
+                ((time - start).num_seconds() as f64 / span.num_seconds() as f64 * dots_x as f64)
+                    .round() as usize
+            } else {
+                0
+            };
+            let y = ((max - value) as f64 / (max - min) as f64 * dots_y as f64).round() as usize;
+            (x, y)
+        };
+        let canvases: Vec<Canvas> = series
+            .iter()
+            .map(|points| {
+                let mut canvas = Canvas::new(plot_width, height);
+                let mut previous = None;
+                for &point in points {
+                    let dot = to_dot(point);
+                    canvas.line(previous.unwrap_or(dot), dot);
+                    previous = Some(dot);
+                }
+                canvas
+            })
+            .collect();
+        let markers = !color && series.len() > 1;
+        let style = |idx: usize| {
+            if color {
+                CompoundStyle::with_fg(SERIES_COLORS[idx % SERIES_COLORS.len()])
+            } else {
+                CompoundStyle::default()
+            }
+        };
+        let axis_style = &skin.paragraph.compound_style;
+
+        let mut text = String::new();
+        for (row, label) in labels.iter().enumerate() {
+            let tick = if label.is_empty() { '│' } else { '┤' };
+            let _ = write!(
+                text,
+                "{} ",
+                axis_style.apply_to(format!("{:>w$}{}", label, tick, w = label_width)),
+            );
+            for col in 0..plot_width {
+                // the last drawn series is on top
+                let top = (0..canvases.len())
+                    .rev()
+                    .find(|&idx| canvases[idx].dots(col, row) != 0);
+                let Some(idx) = top else {
+                    text.push(' ');
+                    continue;
+                };
+                let c = if markers {
+                    SERIES_MARKERS[idx % SERIES_MARKERS.len()]
+                } else {
+                    let dots = canvases
+                        .iter()
+                        .fold(0, |dots, canvas| dots | canvas.dots(col, row));
+                    char::from_u32(BRAILLE_BASE + dots as u32).unwrap_or(' ')
+                };
+                let _ = write!(text, "{}", style(idx).apply_to(c));
+            }
+            text.push('\n');
+        }
+        // the part of the x axis covered by reconstructed observations is dotted
+        let reconstructed_cols = self
+            .extract
+            .lines
+            .iter()
+            .filter(|line| line.reconstructed)
+            .map(|line| to_dot((line.time, min)).0 / 2 + 1)
+            .max();
+        let axis: String = (0..plot_width + 1)
+            .map(|col| match reconstructed_cols {
+                Some(cols) if col > 0 && col <= cols => '┄',
+                _ => '─',
+            })
+            .collect();
+        let _ = writeln!(
+            text,
+            "{:w$}{}",
+            "",
+            axis_style.apply_to(format!("└{}", axis)),
+            w = label_width,
+        );
+        let start_label = time_label(start, span);
+        let end_label = time_label(end, span);
+        let gap = (plot_width + 2).saturating_sub(start_label.len() + end_label.len());
+        let x_labels = if span > Duration::zero() && gap > 0 {
+            format!("{}{:gap$}{}", start_label, "", end_label, gap = gap)
+        } else {
+            start_label
+        };
+        let _ = writeln!(
+            text,
+            "{:w$}{}",
+            "",
+            axis_style.apply_to(x_labels),
+            w = label_width,
+        );
+
+        // the legend
+        for (idx, name) in self.extract.names.iter().enumerate() {
+            let sample = if markers {
+                SERIES_MARKERS[idx % SERIES_MARKERS.len()].to_string()
+            } else {
+                "⣀⣀".to_string()
+            };
+            let _ = writeln!(
+                text,
+                "{:w$} {} {}",
+                "",
+                style(idx).apply_to(sample),
+                skin.bold.apply_to(name),
+                w = label_width,
+            );
+        }
+        if reconstructed_cols.is_some() {
+            let _ = writeln!(
+                text,
+                "{:w$} {} {}",
+                "",
+                axis_style.apply_to("┄┄"),
+                skin.italic
+                    .apply_to("reconstructed from the dates of the stars"),
+                w = label_width,
+            );
+        }
+        text
+    }
+}
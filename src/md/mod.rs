@@ -1,7 +1,13 @@
 mod change_report;
+mod follow_report;
+mod graph;
 mod skin;
+mod starred_report;
 
 pub use {
     change_report::*,
+    follow_report::*,
+    graph::*,
     skin::*,
+    starred_report::*,
 };
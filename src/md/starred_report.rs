@@ -0,0 +1,90 @@
+use {
+    crate::*,
+    chrono::Local,
+    minimad::{
+        OwningTemplateExpander,
+        TextTemplate,
+    },
+    termimad::*,
+};
+
+static TEMPLATE: &str = r#"
+${starred-count} repositories starred by the people you follow
+${cropped
+${kept-count} most recent ones:
+}
+|:-:|:-:|:-:|
+|**who**|**starred**|**when**|**stars**|**url** (ctrl-click to open)|
+|-:|:-|:-:|-:|:-|
+${starred
+|${who}|**${repo}**|${when}|${stars}|${url}|
+}
+|-|-|-|-|-|
+"#;
+
+/// the "what your people starred" section, listing the
+/// repositories recently starred by the users whose starred
+/// feed is followed
+pub struct StarredReport<'c> {
+    items: &'c [StarredFeedItem],
+    conf: &'c Conf,
+    max_rows: usize,
+}
+
+impl<'c> StarredReport<'c> {
+    pub fn new(
+        items: &'c [StarredFeedItem],
+        conf: &'c Conf,
+        max_rows: usize,
+    ) -> Self {
+        Self {
+            items,
+            conf,
+            max_rows,
+        }
+    }
+    pub fn print(
+        &self,
+        skin: &MadSkin,
+    ) {
+        let (width, _) = terminal_size();
+        print!("{}", self.render(skin, width as usize));
+    }
+    /// render the section as text fitting the given width, or
+    /// nothing when no repository was starred
+    pub fn render(
+        &self,
+        skin: &MadSkin,
+        width: usize,
+    ) -> String {
+        if self.items.is_empty() {
+            return String::new();
+        }
+        let mut expander = OwningTemplateExpander::new();
+        expander
+            .set_default("")
+            .set("starred-count", self.items.len());
+        // most recent first
+        for item in self.items.iter().rev().take(self.max_rows) {
+            expander
+                .sub("starred")
+                .set("who", &item.user_id)
+                .set("repo", &item.starred.repo)
+                .set(
+                    "when",
+                    item.starred
+                        .starred_at
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M"),
+                )
+                .set("stars", item.starred.stars)
+                .set("url", item.url(self.conf).unwrap_or_default());
+        }
+        if self.items.len() > self.max_rows {
+            expander.sub("cropped").set("kept-count", self.max_rows);
+        }
+        let template = TextTemplate::from(TEMPLATE);
+        let text = expander.expand(&template);
+        FmtText::from_text(skin, text, Some(width)).to_string()
+    }
+}
@@ -0,0 +1,42 @@
+mod payload;
+mod sink;
+mod smtp;
+
+pub use {
+    payload::*,
+    sink::*,
+    smtp::*,
+};
+
+use crate::*;
+
+/// send the interesting changes to the configured sinks.
+///
+/// Failures are reported but don't stop the other sinks, nor
+/// the caller: the changes are already stored anyway.
+pub async fn notify(
+    conf: &Conf,
+    changes: &[RepoChange],
+) {
+    let sinks = match Sink::all(conf) {
+        Ok(sinks) => sinks,
+        Err(e) => {
+            eprintln!("Invalid notification configuration: {:?}", e);
+            return;
+        }
+    };
+    for sink in sinks {
+        let mut selected: Vec<RepoChange> = changes
+            .iter()
+            .filter(|change| sink.accepts(change))
+            .cloned()
+            .collect();
+        if selected.is_empty() {
+            continue;
+        }
+        selected.sort_by(|a, b| b.interest().partial_cmp(&a.interest()).unwrap());
+        if let Err(e) = sink.send(&selected, conf).await {
+            eprintln!("Notification to {} failed: {:?}", sink, e);
+        }
+    }
+}
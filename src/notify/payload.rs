@@ -0,0 +1,20 @@
+use {
+    crate::*,
+    serde::Serialize,
+};
+
+/// the JSON document sent to webhooks and commands
+#[derive(Debug, Serialize)]
+pub struct Notification {
+    pub changes: Vec<ChangeRecord>,
+}
+
+impl Notification {
+    pub fn new(
+        changes: &[RepoChange],
+        conf: &Conf,
+    ) -> Self {
+        let changes = changes.iter().map(|change| change.record(conf)).collect();
+        Self { changes }
+    }
+}
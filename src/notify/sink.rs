@@ -0,0 +1,242 @@
+use {
+    crate::*,
+    anyhow::*,
+    chrono::Utc,
+    std::{
+        fmt,
+        process::Stdio,
+        time::Duration,
+    },
+    tokio::{
+        io::AsyncWriteExt,
+        process::Command,
+        time::timeout,
+    },
+};
+
+/// the prop key for the URL to which changes are POSTed as JSON
+pub static NOTIFY_WEBHOOK_KEY: &str = "notify-webhook";
+
+/// the prop key for the shell command receiving changes as JSON on stdin
+pub static NOTIFY_COMMAND_KEY: &str = "notify-command";
+
+/// the prop key for the comma separated recipients of the email report
+pub static NOTIFY_EMAIL_KEY: &str = "notify-email";
+
+/// the prop key for the SMTP server, as `host` or `host:port`
+pub static SMTP_SERVER_KEY: &str = "smtp-server";
+pub static SMTP_USER_KEY: &str = "smtp-user";
+pub static SMTP_PASSWORD_KEY: &str = "smtp-password";
+/// the prop key for the sender of the email, defaulting to the SMTP user
+pub static SMTP_FROM_KEY: &str = "smtp-from";
+
+/// the prop key for the number of seconds a sink may take
+/// to receive the changes
+pub static NOTIFY_TIMEOUT_KEY: &str = "notify-timeout";
+
+/// the suffix of the prop key setting the minimal interest of the
+/// changes sent to a sink (eg `notify-webhook-threshold`)
+static THRESHOLD_SUFFIX: &str = "-threshold";
+
+/// the minimal interest of notified changes, when not configured
+pub const DEFAULT_THRESHOLD: f64 = 2.0;
+
+/// the time a sink may take, when not configured
+pub const DEFAULT_NOTIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// the port used when the SMTP server is given without one
+const DEFAULT_SMTP_PORT: u16 = 587;
+
+/// the width of the report sent by email
+const EMAIL_WIDTH: usize = 120;
+
+/// where changes are sent
+#[derive(Debug)]
+pub enum SinkKind {
+    Webhook(String),
+    Command(String),
+    Email(SmtpConf),
+}
+
+/// a configured destination of notifications, receiving
+/// only the changes interesting enough
+#[derive(Debug)]
+pub struct Sink {
+    pub kind: SinkKind,
+    pub threshold: f64,
+    /// after this time, the sending is abandoned (and the command killed)
+    pub timeout: Duration,
+}
+
+impl fmt::Display for Sink {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match &self.kind {
+            SinkKind::Webhook(url) => write!(f, "webhook {}", url),
+            SinkKind::Command(command) => write!(f, "command {:?}", command),
+            SinkKind::Email(smtp) => write!(f, "email to {}", smtp.to.join(", ")),
+        }
+    }
+}
+
+impl Sink {
+    /// read the sinks configured in the props
+    pub fn all(conf: &Conf) -> Result<Vec<Self>> {
+        let timeout = match conf.get(NOTIFY_TIMEOUT_KEY) {
+            Some(value) => value.parse().map(Duration::from_secs).map_err(|_| {
+                anyhow!(
+                    "Invalid value for {}: {:?} isn't a number of seconds",
+                    NOTIFY_TIMEOUT_KEY,
+                    value
+                )
+            })?,
+            None => DEFAULT_NOTIFY_TIMEOUT,
+        };
+        let mut sinks = Vec::new();
+        if let Some(url) = conf.get(NOTIFY_WEBHOOK_KEY) {
+            sinks.push(Self {
+                kind: SinkKind::Webhook(url.to_string()),
+                threshold: threshold(conf, NOTIFY_WEBHOOK_KEY)?,
+                timeout,
+            });
+        }
+        if let Some(command) = conf.get(NOTIFY_COMMAND_KEY) {
+            sinks.push(Self {
+                kind: SinkKind::Command(command.to_string()),
+                threshold: threshold(conf, NOTIFY_COMMAND_KEY)?,
+                timeout,
+            });
+        }
+        if let Some(recipients) = conf.get(NOTIFY_EMAIL_KEY) {
+            sinks.push(Self {
+                kind: SinkKind::Email(smtp_conf(conf, recipients)?),
+                threshold: threshold(conf, NOTIFY_EMAIL_KEY)?,
+                timeout,
+            });
+        }
+        Ok(sinks)
+    }
+    /// tell whether the change is interesting enough for this sink
+    pub fn accepts(
+        &self,
+        change: &RepoChange,
+    ) -> bool {
+        change.interest() >= self.threshold
+    }
+    /// send the changes, which are assumed to be already filtered,
+    /// failing if it takes longer than the timeout
+    pub async fn send(
+        &self,
+        changes: &[RepoChange],
+        conf: &Conf,
+    ) -> Result<()> {
+        timeout(self.timeout, self.deliver(changes, conf))
+            .await
+            .map_err(|_| anyhow!("No completion after {}s", self.timeout.as_secs()))?
+    }
+    async fn deliver(
+        &self,
+        changes: &[RepoChange],
+        conf: &Conf,
+    ) -> Result<()> {
+        match &self.kind {
+            SinkKind::Webhook(url) => {
+                let notification = Notification::new(changes, conf);
+                reqwest::Client::new()
+                    .post(url)
+                    .header("User-Agent", "starry")
+                    .json(&notification)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            SinkKind::Command(command) => {
+                let json = serde_json::to_vec(&Notification::new(changes, conf))?;
+                let mut child = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .stdin(Stdio::piped())
+                    // so that it doesn't survive a timeout
+                    .kill_on_drop(true)
+                    .spawn()?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(&json).await?;
+                }
+                let status = child.wait().await?;
+                if !status.success() {
+                    bail!("Command {:?} failed: {}", command, status);
+                }
+            }
+            SinkKind::Email(smtp) => {
+                let skin = make_skin(false);
+                let report = ChangeReport::new(changes, conf, changes.len());
+                let mail = Mail {
+                    subject: format!(
+                        "starry: {} changes ({})",
+                        changes.len(),
+                        Utc::now().format("%Y-%m-%d"),
+                    ),
+                    body: report.render(&skin, EMAIL_WIDTH),
+                };
+                send_mail(smtp, &mail).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// read the threshold of the sink configured with the given key
+fn threshold(
+    conf: &Conf,
+    sink_key: &str,
+) -> Result<f64> {
+    let key = format!("{}{}", sink_key, THRESHOLD_SUFFIX);
+    match conf.get(&key) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| anyhow!("Invalid value for {}: {:?} isn't a number", key, value)),
+        None => Ok(DEFAULT_THRESHOLD),
+    }
+}
+
+fn smtp_conf(
+    conf: &Conf,
+    recipients: &str,
+) -> Result<SmtpConf> {
+    let Some(server) = conf.get(SMTP_SERVER_KEY) else {
+        bail!("{} is set but not {}", NOTIFY_EMAIL_KEY, SMTP_SERVER_KEY);
+    };
+    let (server, port) = match server.split_once(':') {
+        Some((server, port)) => (
+            server,
+            port.parse()
+                .map_err(|_| anyhow!("Invalid port in {}: {:?}", SMTP_SERVER_KEY, port))?,
+        ),
+        None => (server, DEFAULT_SMTP_PORT),
+    };
+    let user = conf.get(SMTP_USER_KEY).map(String::from);
+    let from = conf
+        .get(SMTP_FROM_KEY)
+        .map(String::from)
+        .or_else(|| user.clone())
+        .ok_or_else(|| anyhow!("Neither {} nor {} is set", SMTP_FROM_KEY, SMTP_USER_KEY))?;
+    let to: Vec<String> = recipients
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect();
+    if to.is_empty() {
+        bail!("No recipient in {}", NOTIFY_EMAIL_KEY);
+    }
+    Ok(SmtpConf {
+        server: server.to_string(),
+        port,
+        user,
+        password: conf.get(SMTP_PASSWORD_KEY).map(String::from),
+        from,
+        to,
+    })
+}
@@ -0,0 +1,176 @@
+use {
+    anyhow::{
+        anyhow,
+        bail,
+        Result,
+    },
+    base64::{
+        engine::general_purpose::STANDARD as BASE64,
+        Engine,
+    },
+    chrono::Utc,
+    tokio::{
+        io::{
+            AsyncBufReadExt,
+            AsyncRead,
+            AsyncWrite,
+            AsyncWriteExt,
+            BufReader,
+        },
+        net::TcpStream,
+    },
+    tokio_native_tls::TlsConnector,
+};
+
+/// the port on which servers expect TLS from the start,
+/// other ports are upgraded with STARTTLS
+const IMPLICIT_TLS_PORT: u16 = 465;
+
+/// what's needed to send a mail
+#[derive(Debug, Clone)]
+pub struct SmtpConf {
+    pub server: String,
+    pub port: u16,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// a plain text mail
+#[derive(Debug)]
+pub struct Mail {
+    pub subject: String,
+    pub body: String,
+}
+
+/// an SMTP conversation over some stream
+struct Session<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+        }
+    }
+    /// read a reply, possibly on several lines, and check its code.
+    /// Return the text of the reply
+    async fn expect(
+        &mut self,
+        code: u16,
+    ) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                bail!("SMTP connection closed");
+            }
+            let line = line.trim_end();
+            let reply_code: u16 = line
+                .get(..3)
+                .and_then(|c| c.parse().ok())
+                .ok_or_else(|| anyhow!("Invalid SMTP reply: {:?}", line))?;
+            if reply_code != code {
+                bail!("Unexpected SMTP reply: {:?}", line);
+            }
+            text.push_str(line.get(4..).unwrap_or(""));
+            text.push('\n');
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok(text);
+            }
+        }
+    }
+    async fn command(
+        &mut self,
+        command: &str,
+        code: u16,
+    ) -> Result<String> {
+        let stream = self.stream.get_mut();
+        stream.write_all(command.as_bytes()).await?;
+        stream.write_all(b"\r\n").await?;
+        stream.flush().await?;
+        self.expect(code).await
+    }
+    /// authenticate if needed, then send the mail
+    async fn deliver(
+        &mut self,
+        conf: &SmtpConf,
+        mail: &Mail,
+    ) -> Result<()> {
+        if let (Some(user), Some(password)) = (&conf.user, &conf.password) {
+            let credentials = BASE64.encode(format!("\0{}\0{}", user, password));
+            self.command(&format!("AUTH PLAIN {}", credentials), 235)
+                .await?;
+        }
+        self.command(&format!("MAIL FROM:<{}>", conf.from), 250)
+            .await?;
+        for to in &conf.to {
+            self.command(&format!("RCPT TO:<{}>", to), 250).await?;
+        }
+        self.command("DATA", 354).await?;
+        self.command(&format!("{}\r\n.", mail.message(conf)), 250)
+            .await?;
+        self.command("QUIT", 221).await?;
+        Ok(())
+    }
+}
+
+impl Mail {
+    /// build the message, headers and base64 encoded body
+    pub fn message(
+        &self,
+        conf: &SmtpConf,
+    ) -> String {
+        let body = BASE64.encode(&self.body);
+        let mut lines = vec![
+            format!("From: {}", conf.from),
+            format!("To: {}", conf.to.join(", ")),
+            format!("Subject: {}", self.subject),
+            format!("Date: {}", Utc::now().to_rfc2822()),
+            "MIME-Version: 1.0".to_string(),
+            "Content-Type: text/plain; charset=utf-8".to_string(),
+            "Content-Transfer-Encoding: base64".to_string(),
+            String::new(),
+        ];
+        // base64 lines never start with a dot, so there's no dot-stuffing to do
+        lines.extend(
+            body.as_bytes()
+                .chunks(76)
+                .map(|chunk| String::from_utf8_lossy(chunk).to_string()),
+        );
+        lines.join("\r\n")
+    }
+}
+
+/// send a mail, over TLS
+pub async fn send_mail(
+    conf: &SmtpConf,
+    mail: &Mail,
+) -> Result<()> {
+    let tcp = TcpStream::connect((conf.server.as_str(), conf.port)).await?;
+    let connector = TlsConnector::from(native_tls::TlsConnector::new()?);
+    if conf.port == IMPLICIT_TLS_PORT {
+        let tls = connector.connect(&conf.server, tcp).await?;
+        let mut session = Session::new(tls);
+        session.expect(220).await?;
+        session.command("EHLO starry", 250).await?;
+        return session.deliver(conf, mail).await;
+    }
+    let mut session = Session::new(tcp);
+    session.expect(220).await?;
+    let capabilities = session.command("EHLO starry", 250).await?;
+    if !capabilities
+        .lines()
+        .any(|c| c.eq_ignore_ascii_case("STARTTLS"))
+    {
+        bail!("SMTP server {} doesn't support STARTTLS", conf.server);
+    }
+    session.command("STARTTLS", 220).await?;
+    let tcp = session.stream.into_inner();
+    let tls = connector.connect(&conf.server, tcp).await?;
+    let mut session = Session::new(tls);
+    session.command("EHLO starry", 250).await?;
+    session.deliver(conf, mail).await
+}
@@ -0,0 +1,170 @@
+//! A local HTTP server, answering requests like a forge API
+//! or a webhook would, shared by the test files.
+
+#![allow(dead_code)]
+
+use {
+    serde_json::Value,
+    std::{
+        io::{
+            BufRead,
+            BufReader,
+            Read,
+            Write,
+        },
+        net::{
+            TcpListener,
+            TcpStream,
+        },
+        sync::{
+            Arc,
+            Mutex,
+        },
+        thread,
+    },
+};
+
+/// a request received by the mock server
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// the path, with the query
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// the answer of the mock server to a request
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    /// the GraphQL query, if the body is one
+    pub fn graphql_query(&self) -> String {
+        serde_json::from_str::<Value>(&self.body)
+            .ok()
+            .and_then(|body| body["query"].as_str().map(|s| s.to_string()))
+            .unwrap_or_default()
+    }
+    pub fn header(
+        &self,
+        name: &str,
+    ) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl Response {
+    pub fn json(body: &str) -> Self {
+        Self::status(200, body)
+    }
+    pub fn status(
+        status: u16,
+        body: &str,
+    ) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: body.to_string(),
+        }
+    }
+    pub fn with_header(
+        mut self,
+        name: &str,
+        value: &str,
+    ) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// a local HTTP server answering every request with a handler,
+/// and keeping the requests
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&Request) -> Response + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let Some(request) = read_request(&stream) else {
+                    continue;
+                };
+                let response = handler(&request);
+                recorded.lock().unwrap().push(request);
+                write_response(&mut stream, &response);
+            }
+        });
+        Self { url, requests }
+    }
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut tokens = line.split_whitespace();
+    let method = tokens.next()?.to_string();
+    let path = tokens.next()?.to_string();
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+    let length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    response: &Response,
+) {
+    let mut head = format!("HTTP/1.1 {} Mock\r\n", response.status);
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.body.len()
+    ));
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(response.body.as_bytes());
+}
@@ -0,0 +1,882 @@
+//! Tests of the database, over temporary directories
+
+use {
+    chrono::{
+        DateTime,
+        Duration,
+        TimeZone,
+        Utc,
+    },
+    starry::*,
+    std::{
+        fs,
+        path::PathBuf,
+    },
+};
+
+/// a temporary directory, removed on drop
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("starry-db-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn time(day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap()
+}
+
+fn repo(
+    name: &str,
+    id: &str,
+    stars: usize,
+) -> RepoObs {
+    RepoObs {
+        id: Some(id.to_string()),
+        ..RepoObs::new(name, stars)
+    }
+}
+
+fn user_obs(
+    login: &str,
+    day: u32,
+    counts: Vec<RepoObs>,
+) -> UserObs {
+    UserObs {
+        user_id: UserId::new(login),
+        time: time(day),
+        counts,
+    }
+}
+
+/// the storages the database tests run on
+const STORAGE_KINDS: [StorageKind; 3] =
+    [StorageKind::Csv, StorageKind::CsvDelta, StorageKind::Sqlite];
+
+/// build a conf following the given users, and a db in a temporary
+/// directory holding the given observations in the given storage
+fn db_with(
+    dir: &TempDir,
+    kind: StorageKind,
+    obss: &[UserObs],
+) -> (Conf, Db) {
+    let mut conf = Conf::read_at(dir.path.join("config.json")).unwrap();
+    conf.props.insert(STORAGE_KEY.to_string(), kind.to_string());
+    let storage = kind.open(&dir.path).unwrap();
+    for uo in obss {
+        storage.write_user_obs(uo, false).unwrap();
+        conf.follow(uo.user_id.clone());
+    }
+    let db = Db::at(&conf, dir.path.clone()).unwrap();
+    (conf, db)
+}
+
+#[test]
+fn write_and_read_round_trip() {
+    let dir = TempDir::new("round-trip");
+    let mut foo = repo("foo", "R1", 12);
+    foo.forks = Some(3);
+    foo.archived = Some(false);
+    let uo = user_obs("alice", 1, vec![foo, RepoObs::new("bar", 0)]);
+    uo.write_in_dir(&dir.path, false).unwrap();
+    let path = dir.path.join(UserObs::filename(uo.time));
+    assert_eq!(UserObs::filename_date(&path), Some(uo.time));
+    let read = UserObs::read_file(&path, uo.user_id.clone(), uo.time).unwrap();
+    assert_eq!(read.counts, uo.counts);
+    assert_eq!(read.sum_metric(Metric::Forks), Some(3));
+    assert_eq!(read.sum().stars, 12);
+}
+
+#[test]
+fn diff_from() {
+    let mut old_baz = repo("baz", "R3", 7);
+    old_baz.archived = Some(false);
+    let mut new_baz = repo("baz", "R3", 7);
+    new_baz.archived = Some(true);
+    let old = user_obs(
+        "alice",
+        1,
+        vec![
+            repo("foo", "R1", 10),
+            repo("bar", "R2", 5),
+            old_baz,
+            repo("gone", "R4", 40),
+        ],
+    );
+    let new = user_obs(
+        "alice",
+        2,
+        vec![
+            repo("foo", "R1", 15),
+            repo("bar-renamed", "R2", 5),
+            new_baz,
+            repo("new", "R5", 1),
+        ],
+    );
+    let changes = new.diff_from(&old);
+    let find = |name: &str| {
+        changes
+            .iter()
+            .find(|c| c.repo_id.name == name)
+            .unwrap_or_else(|| panic!("no change for {}", name))
+    };
+    assert_eq!(changes.len(), 5);
+    let foo = find("foo");
+    assert_eq!(foo.kind, ChangeKind::Stars);
+    assert_eq!((foo.old_stars, foo.new_stars), (Some(10), Some(15)));
+    assert_eq!(
+        find("bar-renamed").kind,
+        ChangeKind::Renamed {
+            old_name: "bar".to_string()
+        },
+    );
+    assert_eq!(find("baz").kind, ChangeKind::Archived);
+    let new_repo = find("new");
+    assert_eq!((new_repo.old_stars, new_repo.new_stars), (None, Some(1)));
+    let gone = find("gone");
+    assert_eq!(gone.kind, ChangeKind::Disappeared);
+    assert_eq!((gone.old_stars, gone.new_stars), (Some(40), None));
+    // nothing changes between identical observations
+    assert!(new.diff_from(&new).is_empty());
+}
+
+#[test]
+fn rewrite_observation() {
+    // rewriting an observation with fewer repositories, as migrations
+    // and backfills do, gives the same result in every storage
+    for kind in STORAGE_KINDS {
+        let dir = TempDir::new(&format!("rewrite-{}", kind));
+        let storage = kind.open(&dir.path).unwrap();
+        storage
+            .write_user_obs(
+                &user_obs(
+                    "alice",
+                    1,
+                    vec![repo("foo", "R1", 10), repo("bar", "R2", 5)],
+                ),
+                false,
+            )
+            .unwrap();
+        storage
+            .write_user_obs(&user_obs("alice", 1, vec![repo("foo", "R1", 12)]), false)
+            .unwrap();
+        let read = storage
+            .read_user_obs(&UserId::new("alice"), time(1))
+            .unwrap();
+        assert_eq!(read.counts, [repo("foo", "R1", 12)], "in {}", kind);
+        let extract = storage.extract_user(&UserId::new("alice")).unwrap();
+        assert_eq!(extract.len(), 1, "in {}", kind);
+        assert_eq!(extract[0].stars, 12, "in {}", kind);
+    }
+}
+
+/// write heartbeats of alice, at the given days
+fn write_heartbeats(
+    storage: &dyn Storage,
+    heartbeats: &[(u32, CheckStatus)],
+) {
+    for &(day, status) in heartbeats {
+        storage
+            .write_heartbeat(&UserId::new("alice"), &Heartbeat::new(time(day), status))
+            .unwrap();
+    }
+}
+
+#[test]
+fn heartbeats() {
+    for kind in STORAGE_KINDS {
+        let dir = TempDir::new(&format!("heartbeats-{}", kind));
+        let storage = kind.open(&dir.path).unwrap();
+        assert!(storage
+            .heartbeats(&UserId::new("alice"))
+            .unwrap()
+            .is_empty());
+        write_heartbeats(
+            &*storage,
+            &[
+                (3, CheckStatus::Changed),
+                (2, CheckStatus::Unchanged),
+                (4, CheckStatus::Failed),
+            ],
+        );
+        let heartbeats: Vec<(DateTime<Utc>, CheckStatus, bool)> = storage
+            .heartbeats(&UserId::new("alice"))
+            .unwrap()
+            .iter()
+            .map(|h| (h.time, h.status, h.is_ok()))
+            .collect();
+        assert_eq!(
+            heartbeats,
+            [
+                (time(2), CheckStatus::Unchanged, true),
+                (time(3), CheckStatus::Changed, true),
+                (time(4), CheckStatus::Failed, false),
+            ],
+            "in {}",
+            kind
+        );
+    }
+}
+
+#[test]
+fn gaps_above_min() {
+    for kind in STORAGE_KINDS {
+        let dir = TempDir::new(&format!("gaps-{}", kind));
+        let (_, db) = db_with(
+            &dir,
+            kind,
+            &[
+                user_obs("alice", 1, vec![repo("foo", "R1", 10)]),
+                user_obs("alice", 3, vec![repo("foo", "R1", 12)]),
+            ],
+        );
+        // a failed check doesn't end a gap
+        let storage = kind.open(&dir.path).unwrap();
+        write_heartbeats(
+            &*storage,
+            &[
+                (2, CheckStatus::Unchanged),
+                (5, CheckStatus::Failed),
+                (8, CheckStatus::Unchanged),
+            ],
+        );
+        let alice = [UserId::new("alice")];
+        let gaps = Gaps::read(&db, &alice, Duration::days(2)).unwrap();
+        let periods: Vec<(&str, DateTime<Utc>)> = gaps
+            .gaps
+            .iter()
+            .map(|gap| (gap.name.as_str(), gap.from))
+            .collect();
+        assert_eq!(
+            periods,
+            [("alice", time(3)), ("alice", time(8))],
+            "in {}",
+            kind
+        );
+        assert_eq!(gaps.gaps[0].to, time(8));
+        // checks exactly `min` apart aren't a gap
+        let gaps = Gaps::read(&db, &alice, Duration::days(1)).unwrap();
+        assert_eq!(gaps.gaps.len(), 2, "in {}", kind);
+        let gaps = Gaps::read(&db, &alice, Duration::hours(12)).unwrap();
+        assert_eq!(gaps.gaps.len(), 4, "in {}", kind);
+    }
+}
+
+#[test]
+fn heartbeats_carry_counts_forward() {
+    for kind in STORAGE_KINDS {
+        let dir = TempDir::new(&format!("carry-{}", kind));
+        let (_, db) = db_with(
+            &dir,
+            kind,
+            &[
+                user_obs(
+                    "alice",
+                    1,
+                    vec![repo("foo", "R1", 10), repo("bar", "R2", 1)],
+                ),
+                user_obs(
+                    "alice",
+                    6,
+                    vec![repo("foo", "R1", 12), repo("bar", "R2", 1)],
+                ),
+            ],
+        );
+        let storage = kind.open(&dir.path).unwrap();
+        write_heartbeats(
+            &*storage,
+            &[
+                (1, CheckStatus::Changed),
+                (2, CheckStatus::Unchanged),
+                (4, CheckStatus::Failed),
+                (5, CheckStatus::Unchanged),
+                (6, CheckStatus::Changed),
+                (7, CheckStatus::Unchanged),
+            ],
+        );
+        let names = vec!["alice".to_string(), "alice/foo".to_string()];
+        let read = |heartbeats: bool| -> Vec<(DateTime<Utc>, Vec<Option<usize>>)> {
+            Extract::read(&db, names.clone(), Metric::Stars, heartbeats)
+                .unwrap()
+                .lines
+                .into_iter()
+                .map(|line| (line.time, line.counts))
+                .collect()
+        };
+        assert_eq!(read(false).len(), 2, "in {}", kind);
+        // there's a line at every successful check, none at the failed one
+        assert_eq!(
+            read(true),
+            [
+                (time(1), vec![Some(11), Some(10)]),
+                (time(2), vec![Some(11), Some(10)]),
+                (time(5), vec![Some(11), Some(10)]),
+                (time(6), vec![Some(13), Some(12)]),
+                (time(7), vec![Some(13), Some(12)]),
+            ],
+            "in {}",
+            kind
+        );
+    }
+}
+
+#[test]
+fn extract_merges_users() {
+    for kind in STORAGE_KINDS {
+        let dir = TempDir::new(&format!("extract-{}", kind));
+        let (_, db) = db_with(
+            &dir,
+            kind,
+            &[
+                user_obs(
+                    "alice",
+                    1,
+                    vec![repo("foo", "R1", 10), repo("bar", "R2", 1)],
+                ),
+                user_obs(
+                    "alice",
+                    3,
+                    vec![repo("foo", "R1", 12), repo("bar", "R2", 1)],
+                ),
+                user_obs("bob", 2, vec![repo("qux", "R3", 100)]),
+                user_obs("bob", 3, vec![repo("qux", "R3", 101)]),
+            ],
+        );
+        let names = vec![
+            "alice".to_string(),
+            "alice/foo".to_string(),
+            "bob/qux".to_string(),
+        ];
+        let extract = Extract::read(&db, names.clone(), Metric::Stars, false).unwrap();
+        assert_eq!(extract.names, names);
+        let lines: Vec<(DateTime<Utc>, Vec<Option<usize>>)> = extract
+            .lines
+            .into_iter()
+            .map(|line| (line.time, line.counts))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (time(1), vec![Some(11), Some(10), None]),
+                (time(2), vec![None, None, Some(100)]),
+                (time(3), vec![Some(13), Some(12), Some(101)]),
+            ],
+            "{}",
+            kind,
+        );
+    }
+}
+
+#[test]
+fn list_users() {
+    for kind in STORAGE_KINDS {
+        let dir = TempDir::new(&format!("list-{}", kind));
+        let (conf, db) = db_with(
+            &dir,
+            kind,
+            &[
+                user_obs("alice", 1, vec![repo("foo", "R1", 10)]),
+                user_obs(
+                    "alice",
+                    2,
+                    vec![repo("foo", "R1", 12), repo("bar", "R2", 3)],
+                ),
+                user_obs("bob", 1, vec![repo("qux", "R3", 100)]),
+            ],
+        );
+        let mut list = List::users(&db, &conf, false, Metric::Stars).unwrap();
+        list.lines.sort_by(|a, b| a.name.cmp(&b.name));
+        let lines: Vec<(&str, usize)> = list
+            .lines
+            .iter()
+            .map(|line| (line.name.as_str(), line.count))
+            .collect();
+        assert_eq!(lines, vec![("alice", 15), ("bob", 100)], "{}", kind);
+        // only alice has enough observations to be drawn
+        let list = List::users(&db, &conf, true, Metric::Stars).unwrap();
+        assert_eq!(list.lines.len(), 1);
+        assert_eq!(list.lines[0].name, "alice");
+    }
+}
+
+#[test]
+fn changes_between() {
+    for kind in STORAGE_KINDS {
+        let dir = TempDir::new(&format!("between-{}", kind));
+        let (conf, db) = db_with(
+            &dir,
+            kind,
+            &[
+                user_obs("alice", 1, vec![repo("foo", "R1", 10)]),
+                user_obs("alice", 3, vec![repo("foo", "R1", 12)]),
+                user_obs("alice", 5, vec![repo("foo", "R1", 20)]),
+                // bob is observed only since the 4th
+                user_obs("bob", 4, vec![repo("qux", "R3", 100)]),
+                user_obs("bob", 5, vec![repo("qux", "R3", 90)]),
+            ],
+        );
+        let owners = conf.stored_owners();
+        let stars = |changes: &[RepoChange]| {
+            let mut stars: Vec<(String, Option<usize>, Option<usize>)> = changes
+                .iter()
+                .map(|c| (c.repo_id.name.clone(), c.old_stars, c.new_stars))
+                .collect();
+            stars.sort();
+            stars
+        };
+        // from the observation of the 1st to the one of the 3rd
+        let changes = db.changes_between(&owners, time(2), time(4)).unwrap();
+        assert_eq!(
+            stars(&changes),
+            vec![("foo".to_string(), Some(10), Some(12))],
+            "{}",
+            kind,
+        );
+        // bob's first observation is his reference
+        let changes = db.changes_between(&owners, time(3), time(6)).unwrap();
+        assert_eq!(
+            stars(&changes),
+            vec![
+                ("foo".to_string(), Some(12), Some(20)),
+                ("qux".to_string(), Some(100), Some(90)),
+            ],
+            "{}",
+            kind,
+        );
+        // no observation in the period
+        let changes = db.changes_between(&owners, time(3), time(3)).unwrap();
+        assert!(changes.is_empty());
+    }
+}
+
+/// fill a storage with observations and all the extensions:
+/// heartbeats, stargazer events, follow observations and starred
+/// repositories
+fn fill(storage: &dyn Storage) {
+    let mut observed = repo("foo", "R1", 10);
+    observed.forks = Some(2);
+    observed.downloads = Some(300);
+    observed.archived = Some(false);
+    let mut reconstructed = repo("foo", "R1", 4);
+    reconstructed.reconstructed = Some(true);
+    for uo in [
+        user_obs("alice", 1, vec![reconstructed]),
+        user_obs("alice", 2, vec![observed, RepoObs::new("bar", 0)]),
+        user_obs("alice", 4, vec![repo("foo", "R1", 12)]),
+        user_obs("bob", 3, vec![repo("qux", "R3", 100)]),
+    ] {
+        storage.write_user_obs(&uo, false).unwrap();
+    }
+    let alice = UserId::new("alice");
+    for (day, status) in [
+        (2, CheckStatus::Changed),
+        (3, CheckStatus::Unchanged),
+        (4, CheckStatus::Failed),
+    ] {
+        storage
+            .write_heartbeat(&alice, &Heartbeat::new(time(day), status))
+            .unwrap();
+    }
+    let foo_id = RepoId::new(alice.clone(), "foo");
+    storage
+        .write_stargazer_events(
+            &foo_id,
+            &[
+                StargazerEvent {
+                    time: time(1),
+                    login: "bob".to_string(),
+                    kind: StargazerEventKind::Star,
+                },
+                StargazerEvent {
+                    time: time(3),
+                    login: "bob".to_string(),
+                    kind: StargazerEventKind::Unstar,
+                },
+            ],
+        )
+        .unwrap();
+    for (day, followers) in [(2, 5), (4, 7)] {
+        let follow_obs = FollowObs {
+            time: time(day),
+            followers,
+            following: 1,
+        };
+        storage.write_follow_obs(&alice, &follow_obs).unwrap();
+    }
+    storage
+        .write_starred(
+            &alice,
+            &[StarredRepo {
+                starred_at: time(3),
+                repo: "carol/zap".to_string(),
+                stars: 42,
+            }],
+        )
+        .unwrap();
+}
+
+/// check two storages hold the same data
+fn assert_same(
+    a: &dyn Storage,
+    b: &dyn Storage,
+) {
+    let mut user_ids = a.user_ids().unwrap();
+    let mut other_ids = b.user_ids().unwrap();
+    user_ids.sort_by_key(|u| u.to_string());
+    other_ids.sort_by_key(|u| u.to_string());
+    assert_eq!(user_ids, other_ids);
+    for user_id in &user_ids {
+        let times = a.user_obs_times(user_id).unwrap();
+        assert_eq!(times, b.user_obs_times(user_id).unwrap());
+        for time in times {
+            let mut counts = a.read_user_obs(user_id, time).unwrap().counts;
+            let mut other_counts = b.read_user_obs(user_id, time).unwrap().counts;
+            counts.sort_by(|x, y| x.repo_name.cmp(&y.repo_name));
+            other_counts.sort_by(|x, y| x.repo_name.cmp(&y.repo_name));
+            assert_eq!(counts, other_counts, "{} at {}", user_id, time);
+        }
+        let heartbeats = |s: &dyn Storage| -> Vec<(DateTime<Utc>, CheckStatus)> {
+            s.heartbeats(user_id)
+                .unwrap()
+                .iter()
+                .map(|h| (h.time, h.status))
+                .collect()
+        };
+        assert_eq!(heartbeats(a), heartbeats(b));
+        let names = a.stargazer_repo_names(user_id).unwrap();
+        assert_eq!(names, b.stargazer_repo_names(user_id).unwrap());
+        for name in names {
+            let repo_id = RepoId::new(user_id.clone(), name);
+            assert_eq!(
+                a.stargazer_events(&repo_id).unwrap(),
+                b.stargazer_events(&repo_id).unwrap(),
+            );
+        }
+        assert_eq!(
+            a.follow_obss(user_id).unwrap(),
+            b.follow_obss(user_id).unwrap()
+        );
+        assert_eq!(a.starred(user_id).unwrap(), b.starred(user_id).unwrap());
+    }
+}
+
+#[test]
+fn migration_round_trip() {
+    let csv_dir = TempDir::new("migration-csv");
+    let sqlite_dir = TempDir::new("migration-sqlite");
+    let back_dir = TempDir::new("migration-back");
+    let csv = StorageKind::Csv.open(&csv_dir.path).unwrap();
+    fill(&*csv);
+    assert_eq!(
+        csv.stargazer_repo_names(&UserId::new("alice")).unwrap(),
+        vec!["foo"]
+    );
+    let sqlite = StorageKind::Sqlite.open(&sqlite_dir.path).unwrap();
+    let report = migrate(&*csv, &*sqlite, true, false).unwrap();
+    assert!(report.is_ok(), "{:?}", report.problems);
+    assert_eq!((report.users, report.read, report.migrated), (2, 4, 4));
+    assert_same(&*csv, &*sqlite);
+    // and back to delta CSV files
+    let back = StorageKind::CsvDelta.open(&back_dir.path).unwrap();
+    let report = migrate(&*sqlite, &*back, true, false).unwrap();
+    assert!(report.is_ok(), "{:?}", report.problems);
+    assert_eq!(report.migrated, 4);
+    assert_same(&*csv, &*back);
+}
+
+#[test]
+fn migration_twice() {
+    // migrating again, eg after a partial failure, doesn't
+    // duplicate what's already in the target
+    let sqlite_dir = TempDir::new("twice-sqlite");
+    let csv_dir = TempDir::new("twice-csv");
+    let sqlite = StorageKind::Sqlite.open(&sqlite_dir.path).unwrap();
+    fill(&*sqlite);
+    for kind in [StorageKind::Csv, StorageKind::CsvDelta] {
+        let _ = fs::remove_dir_all(&csv_dir.path);
+        let csv = kind.open(&csv_dir.path).unwrap();
+        for _ in 0..2 {
+            let report = migrate(&*sqlite, &*csv, true, false).unwrap();
+            assert!(report.is_ok(), "{:?}", report.problems);
+            assert_eq!(report.migrated, 4);
+        }
+        assert_same(&*sqlite, &*csv);
+        let alice = UserId::new("alice");
+        assert_eq!(csv.heartbeats(&alice).unwrap().len(), 3, "in {}", kind);
+        assert_eq!(csv.follow_obss(&alice).unwrap().len(), 2, "in {}", kind);
+        assert_eq!(csv.starred(&alice).unwrap().len(), 1, "in {}", kind);
+        let foo_id = RepoId::new(alice, "foo");
+        assert_eq!(
+            csv.stargazer_events(&foo_id).unwrap().len(),
+            2,
+            "in {}",
+            kind
+        );
+    }
+}
+
+#[test]
+fn migration_dry_run() {
+    let csv_dir = TempDir::new("dry-run-csv");
+    let sqlite_dir = TempDir::new("dry-run-sqlite");
+    let csv = StorageKind::Csv.open(&csv_dir.path).unwrap();
+    fill(&*csv);
+    let sqlite = StorageKind::Sqlite.open(&sqlite_dir.path).unwrap();
+    let report = migrate(&*csv, &*sqlite, false, false).unwrap();
+    assert!(report.is_ok(), "{:?}", report.problems);
+    assert_eq!((report.read, report.migrated), (4, 0));
+    assert!(sqlite.user_ids().unwrap().is_empty());
+}
+
+#[test]
+fn migration_verification() {
+    let csv_dir = TempDir::new("verification-csv");
+    let sqlite_dir = TempDir::new("verification-sqlite");
+    let csv = StorageKind::Csv.open(&csv_dir.path).unwrap();
+    fill(&*csv);
+    // an unreadable source file is reported, the other ones are migrated
+    fs::write(
+        csv_dir
+            .path
+            .join("stars/bob")
+            .join(UserObs::filename(time(3))),
+        "repo_name,stars\nqux,many\n",
+    )
+    .unwrap();
+    // the target already holds another repository at one of
+    // the times: the migrated observation replaces it
+    let sqlite = StorageKind::Sqlite.open(&sqlite_dir.path).unwrap();
+    sqlite
+        .write_user_obs(&user_obs("alice", 4, vec![repo("stale", "R9", 1)]), false)
+        .unwrap();
+    let report = migrate(&*csv, &*sqlite, true, false).unwrap();
+    assert!(!report.is_ok());
+    assert_eq!((report.read, report.migrated), (3, 3));
+    assert_eq!(report.problems.len(), 1, "{:?}", report.problems);
+    let problem = &report.problems[0];
+    assert!(
+        problem.starts_with("unreadable observation of bob")
+            && problem.ends_with("invalid digit found in string"),
+        "{}",
+        problem
+    );
+    let alice = sqlite
+        .read_user_obs(&UserId::new("alice"), time(4))
+        .unwrap();
+    assert!(alice.repo("stale").is_none(), "{:?}", alice);
+}
+
+/// observations of alice where a repository appears,
+/// another one changes, then is removed
+fn delta_series() -> Vec<UserObs> {
+    vec![
+        user_obs(
+            "alice",
+            1,
+            vec![repo("foo", "R1", 10), repo("bar", "R2", 5)],
+        ),
+        user_obs(
+            "alice",
+            2,
+            vec![repo("foo", "R1", 12), repo("bar", "R2", 5)],
+        ),
+        user_obs(
+            "alice",
+            3,
+            vec![
+                repo("foo", "R1", 12),
+                repo("bar", "R2", 5),
+                repo("baz", "R3", 1),
+            ],
+        ),
+        user_obs(
+            "alice",
+            4,
+            vec![repo("foo", "R1", 15), repo("baz", "R3", 2)],
+        ),
+    ]
+}
+
+/// whether the files of alice, in time order, are deltas
+fn delta_flags(storage: &CsvStorage) -> Vec<bool> {
+    let user_dir = storage.user_stars_dir(&UserId::new("alice"));
+    ObsFile::list(&user_dir)
+        .unwrap()
+        .iter()
+        .map(|file| file.delta)
+        .collect()
+}
+
+fn assert_replayed<'o>(
+    storage: &CsvStorage,
+    obss: impl IntoIterator<Item = &'o UserObs>,
+) {
+    for uo in obss {
+        let read = storage.read_user_obs(&uo.user_id, uo.time).unwrap();
+        assert_eq!(read.counts, uo.counts, "at {}", uo.time);
+    }
+}
+
+#[test]
+fn delta_replay() {
+    let dir = TempDir::new("delta-replay");
+    let storage = CsvStorage::new(&dir.path, true);
+    let obss = delta_series();
+    for uo in &obss {
+        storage.write_user_obs(uo, false).unwrap();
+    }
+    assert_eq!(delta_flags(&storage), [false, true, true, true]);
+    assert_replayed(&storage, &obss);
+    let extract = storage.extract_user(&UserId::new("alice")).unwrap();
+    let stars: Vec<usize> = extract.iter().map(|dated_obs| dated_obs.stars).collect();
+    assert_eq!(stars, [15, 17, 18, 17]);
+}
+
+#[test]
+fn delta_removal_rows() {
+    let dir = TempDir::new("delta-removal");
+    let storage = CsvStorage::new(&dir.path, true);
+    for uo in &delta_series() {
+        storage.write_user_obs(uo, false).unwrap();
+    }
+    // a delta holds only the changed repositories, and the
+    // removed ones as rows with empty counts
+    let user_dir = storage.user_stars_dir(&UserId::new("alice"));
+    let path = user_dir.join(UserObs::delta_filename(time(4)));
+    let deltas = UserObs::read_delta_file(&path).unwrap();
+    assert_eq!(
+        deltas,
+        [
+            RepoDelta::Set(repo("foo", "R1", 15)),
+            RepoDelta::Set(repo("baz", "R3", 2)),
+            RepoDelta::Removed("bar".to_string()),
+        ]
+    );
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(
+        content
+            .lines()
+            .any(|line| line.starts_with("bar,") && line.ends_with(",,")),
+        "{}",
+        content
+    );
+    let path = user_dir.join(UserObs::delta_filename(time(2)));
+    let deltas = UserObs::read_delta_file(&path).unwrap();
+    assert_eq!(deltas, [RepoDelta::Set(repo("foo", "R1", 12))]);
+}
+
+#[test]
+fn delta_keyframe_cadence() {
+    let dir = TempDir::new("delta-keyframes");
+    let storage = CsvStorage::new(&dir.path, true);
+    let count = KEYFRAME_INTERVAL + 3;
+    for day in 1..=count {
+        let uo = user_obs("alice", day as u32, vec![repo("foo", "R1", day)]);
+        storage.write_user_obs(&uo, false).unwrap();
+    }
+    // a full file, KEYFRAME_INTERVAL deltas, then a new full file
+    let flags = delta_flags(&storage);
+    assert_eq!(flags.len(), count);
+    let keyframes: Vec<usize> = (0..count).filter(|&idx| !flags[idx]).collect();
+    assert_eq!(keyframes, [0, KEYFRAME_INTERVAL + 1]);
+    let last = storage
+        .read_user_obs(&UserId::new("alice"), time(count as u32))
+        .unwrap();
+    assert_eq!(last.counts, [repo("foo", "R1", count)]);
+}
+
+#[test]
+fn older_observations_are_written_whole() {
+    let dir = TempDir::new("delta-older");
+    let storage = CsvStorage::new(&dir.path, true);
+    let obss = delta_series();
+    for uo in &obss[1..] {
+        storage.write_user_obs(uo, false).unwrap();
+    }
+    assert_eq!(delta_flags(&storage), [false, true, true]);
+    // a reconstructed observation, older than all the other ones
+    storage.write_user_obs(&obss[0], false).unwrap();
+    assert_eq!(delta_flags(&storage), [false, false, true, true]);
+    assert_replayed(&storage, &obss);
+    // an observation between two others: the delta following
+    // it is rewritten whole, as it applied to the previous file
+    let inserted = user_obs(
+        "alice",
+        2,
+        vec![repo("foo", "R1", 11), repo("bar", "R2", 5)],
+    );
+    let dir = TempDir::new("delta-inserted");
+    let storage = CsvStorage::new(&dir.path, true);
+    for uo in obss.iter().filter(|uo| uo.time != inserted.time) {
+        storage.write_user_obs(uo, false).unwrap();
+    }
+    storage.write_user_obs(&inserted, false).unwrap();
+    assert_eq!(delta_flags(&storage), [false, false, false, true]);
+    assert_replayed(&storage, [&obss[0], &inserted, &obss[2], &obss[3]]);
+}
+
+#[test]
+fn extract_skips_unreadable_files() {
+    let dir = TempDir::new("delta-unreadable");
+    let delta_storage = CsvStorage::new(&dir.path, true);
+    let full_storage = CsvStorage::new(&dir.path, false);
+    let obss = delta_series();
+    delta_storage.write_user_obs(&obss[0], false).unwrap();
+    delta_storage.write_user_obs(&obss[1], false).unwrap();
+    full_storage.write_user_obs(&obss[2], false).unwrap();
+    delta_storage.write_user_obs(&obss[3], false).unwrap();
+    assert_eq!(delta_flags(&delta_storage), [false, true, false, true]);
+    // the unreadable delta is skipped, the following keyframe
+    // and the delta after it are read
+    let user_dir = delta_storage.user_stars_dir(&UserId::new("alice"));
+    fs::write(
+        user_dir.join(UserObs::delta_filename(time(2))),
+        "repo_name,stars\nfoo,many\n",
+    )
+    .unwrap();
+    let extract = delta_storage.extract_user(&UserId::new("alice")).unwrap();
+    let read: Vec<(DateTime<Utc>, usize)> = extract
+        .iter()
+        .map(|dated_obs| (dated_obs.time, dated_obs.stars))
+        .collect();
+    assert_eq!(read, [(time(1), 15), (time(3), 18), (time(4), 17)]);
+    let lines = delta_storage
+        .extract_user_query(&UserId::new("alice"), vec!["foo", "baz"], Metric::Stars)
+        .unwrap();
+    let read: Vec<_> = lines
+        .into_iter()
+        .map(|line| (line.time, line.sum, line.counts))
+        .collect();
+    assert_eq!(
+        read,
+        [
+            (time(1), Some(15), vec![Some(10), None]),
+            (time(3), Some(18), vec![Some(12), Some(1)]),
+            (time(4), Some(17), vec![Some(15), Some(2)]),
+        ]
+    );
+    // with an unreadable keyframe, the deltas following it are skipped
+    fs::write(
+        user_dir.join(UserObs::filename(time(3))),
+        "repo_name,stars\nfoo,many\n",
+    )
+    .unwrap();
+    let extract = delta_storage.extract_user(&UserId::new("alice")).unwrap();
+    assert_eq!(extract.len(), 1);
+    let lines = delta_storage
+        .extract_user_query(&UserId::new("alice"), vec!["foo"], Metric::Stars)
+        .unwrap();
+    assert_eq!(lines.len(), 1);
+}
@@ -0,0 +1,544 @@
+//! End to end tests of the forge clients, against a local server
+//! mocking the APIs of the forges.
+
+mod common;
+
+use {
+    chrono::{
+        TimeZone,
+        Utc,
+    },
+    common::*,
+    starry::{
+        allowed_concurrency,
+        RateBudget,
+        RateLimit,
+        MAX_CONCURRENCY,
+    },
+    std::{
+        fs,
+        path::PathBuf,
+        process::{
+            Command,
+            Output,
+        },
+        sync::{
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+            Arc,
+        },
+        thread,
+        time::Duration,
+    },
+};
+
+/// an isolated configuration and database, without fixtures
+struct Sandbox {
+    root: PathBuf,
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("starry-forge-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        Self { root }
+    }
+    /// declare the host `mock`, of the given forge, with its API
+    /// on the mock server
+    fn set_host(
+        &self,
+        forge: &str,
+        api_url: &str,
+    ) {
+        self.run_ok(&["set", "forge@mock", forge]);
+        self.run_ok(&["set", "api-url@mock", api_url]);
+        let token_key = match forge {
+            "github" => "github-api-token@mock",
+            _ => "api-token@mock",
+        };
+        self.run_ok(&["set", token_key, "secret"]);
+    }
+    fn run(
+        &self,
+        args: &[&str],
+    ) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_starry"))
+            .arg("--config")
+            .arg(self.root.join("config.json"))
+            .arg("--data-dir")
+            .arg(self.root.join("data"))
+            .args(args)
+            .env_remove("STARRY_FIXTURES")
+            .output()
+            .unwrap()
+    }
+    fn run_ok(
+        &self,
+        args: &[&str],
+    ) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "starry {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr),
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+/// answer the queries of a GitHub client about the user alice,
+/// which has the repositories foo and gone
+fn github_alice(query: &str) -> Option<Response> {
+    if query.contains("repositoryOwner") {
+        return Some(Response::json(
+            r#"{"data":{"repositoryOwner":{"__typename":"User"}}}"#,
+        ));
+    }
+    if query.contains("followers {") {
+        return Some(Response::json(
+            r#"{"data":{"user":{"name":"Alice","repositories":{"totalCount":2},
+            "followers":{"totalCount":7},"following":{"totalCount":1}}}}"#,
+        ));
+    }
+    if query.contains("repositories(") {
+        return Some(Response::json(
+            r#"{"data":{"user":{"repositories":{"totalCount":2,"nodes":[
+                {"id":"R1","name":"foo","isArchived":false,"stargazers":{"totalCount":3}},
+                {"id":"R2","name":"gone","isArchived":false,"stargazers":{"totalCount":2}}
+            ],"pageInfo":{"hasNextPage":false}}}}}"#,
+        ));
+    }
+    None
+}
+
+#[test]
+fn github_star_history_errors() {
+    let server = MockServer::start(|request| {
+        let query = request.graphql_query();
+        if query.contains("stargazers(") && query.contains(r#"name:"foo""#) {
+            return Response::json(
+                r#"{"data":null,"errors":[{"type":"FORBIDDEN","message":"Resource not accessible by integration"}]}"#,
+            );
+        }
+        if query.contains("stargazers(") {
+            return Response::json(
+                r#"{"data":{"repository":null},"errors":[{"type":"NOT_FOUND","message":"Could not resolve to a Repository"}]}"#,
+            );
+        }
+        github_alice(&query).unwrap_or_else(|| Response::status(400, "{}"))
+    });
+    let sandbox = Sandbox::new("history");
+    sandbox.set_host("github", &format!("{}/graphql", server.url));
+    let output = sandbox.run(&["backfill", "alice@mock"]);
+    assert!(!output.status.success());
+    let err = stderr(&output);
+    // the real error is told, not hidden behind a "not found"
+    assert!(
+        err.contains("Resource not accessible by integration"),
+        "{}",
+        err
+    );
+    assert!(
+        !err.contains("Repository alice@mock/foo not found"),
+        "{}",
+        err
+    );
+    // while a missing repository is reported as such
+    assert!(
+        err.contains("Repository alice@mock/gone not found"),
+        "{}",
+        err
+    );
+    assert!(
+        server.requests().iter().all(|r| r.method == "POST"),
+        "{:?}",
+        server.requests()
+    );
+}
+
+#[test]
+fn github_starred_errors() {
+    let server = MockServer::start(|request| {
+        let query = request.graphql_query();
+        if query.contains("starredRepositories") && query.contains(r#""alice""#) {
+            return Response::json(
+                r#"{"data":null,"errors":[{"type":"FORBIDDEN","message":"Resource not accessible by integration"}]}"#,
+            );
+        }
+        if query.contains("starredRepositories") {
+            return Response::json(
+                r#"{"data":{"user":null},"errors":[{"type":"NOT_FOUND","message":"Could not resolve to a User"}]}"#,
+            );
+        }
+        github_alice(&query).unwrap_or_else(|| Response::status(400, "{}"))
+    });
+    let sandbox = Sandbox::new("starred");
+    sandbox.set_host("github", &format!("{}/graphql", server.url));
+    sandbox.run_ok(&["track", "alice@mock"]);
+    sandbox.run_ok(&["track", "ghost@mock"]);
+    let output = sandbox.run(&["gaze"]);
+    let err = stderr(&output);
+    assert!(
+        err.contains("Resource not accessible by integration"),
+        "{}",
+        err
+    );
+    assert!(!err.contains("User alice@mock not found"), "{}", err);
+    assert!(err.contains("User ghost@mock not found"), "{}", err);
+}
+
+#[test]
+fn github_repo_batch_errors() {
+    // how the batch query of the followed repositories is answered
+    let mode = Arc::new(AtomicUsize::new(0));
+    let answer = mode.clone();
+    let server = MockServer::start(move |request| {
+        let query = request.graphql_query();
+        if !query.contains(" r0: ") {
+            return Response::json(r#"{"data":{"repository":{"stargazers":{"totalCount":3}}}}"#);
+        }
+        let foo = r#"{"id":"R1","name":"foo","isArchived":false,"stargazers":{"totalCount":3}}"#;
+        let gone = r#"{"id":"R2","name":"gone","isArchived":false,"stargazers":{"totalCount":2}}"#;
+        Response::json(&match answer.load(Ordering::SeqCst) {
+            0 => format!(r#"{{"data":{{"r0":{},"r1":{}}}}}"#, foo, gone),
+            1 => format!(
+                r#"{{"data":{{"r0":{},"r1":null}},"errors":[{{"type":"FORBIDDEN","path":["r1"],"message":"Resource not accessible by integration"}}]}}"#,
+                foo,
+            ),
+            2 => r#"{"data":null,"errors":[{"message":"Something went wrong"}]}"#.to_string(),
+            _ => format!(
+                r#"{{"data":{{"r0":{},"r1":null}},"errors":[{{"type":"NOT_FOUND","path":["r1"],"message":"Could not resolve to a Repository"}}]}}"#,
+                foo,
+            ),
+        })
+    });
+    let sandbox = Sandbox::new("batch");
+    sandbox.set_host("github", &format!("{}/graphql", server.url));
+    sandbox.run_ok(&["follow", "alice@mock/foo"]);
+    sandbox.run_ok(&["follow", "alice@mock/gone"]);
+    sandbox.run_ok(&["gaze"]);
+    let observations = || sandbox.run_ok(&["extract", "alice@mock"]).lines().count() - 1;
+    assert_eq!(observations(), 1);
+    // a failed batch, even with partial data, writes nothing
+    // and reports no disappeared repository
+    for failure in [1, 2] {
+        mode.store(failure, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(1100));
+        let output = sandbox.run(&["gaze"]);
+        let err = stderr(&output);
+        assert!(err.contains("Error reading repository changes"), "{}", err);
+        let out = String::from_utf8_lossy(&output.stdout);
+        assert!(!out.contains("disappeared"), "{}", out);
+        assert_eq!(observations(), 1);
+    }
+    // while a repository GitHub doesn't find has disappeared
+    mode.store(3, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(1100));
+    let out = sandbox.run_ok(&["gaze", "--format", "csv"]);
+    assert!(out.contains("alice@mock,gone,disappeared,2,"), "{}", out);
+    assert_eq!(observations(), 2);
+}
+
+#[test]
+fn github_secondary_rate_limit_is_retried() {
+    let tries = AtomicUsize::new(0);
+    let server = MockServer::start(move |request| {
+        let query = request.graphql_query();
+        if query.contains("repositoryOwner") && tries.fetch_add(1, Ordering::SeqCst) == 0 {
+            return Response::status(
+                403,
+                r#"{"message":"You have exceeded a secondary rate limit. Please wait a few minutes before you try again."}"#,
+            )
+            .with_header("Retry-After", "1");
+        }
+        github_alice(&query).unwrap_or_else(|| Response::status(400, "{}"))
+    });
+    let sandbox = Sandbox::new("secondary");
+    sandbox.set_host("github", &format!("{}/graphql", server.url));
+    sandbox.run_ok(&["track", "alice@mock"]);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn github_forbidden_is_not_retried() {
+    let server = MockServer::start(|_| {
+        Response::status(
+            403,
+            r#"{"message":"Resource not accessible by integration"}"#,
+        )
+    });
+    let sandbox = Sandbox::new("forbidden");
+    sandbox.set_host("github", &format!("{}/graphql", server.url));
+    let output = sandbox.run(&["track", "alice@mock"]);
+    assert!(!output.status.success());
+    let err = stderr(&output);
+    assert!(err.contains("403"), "{}", err);
+    assert!(
+        err.contains("Resource not accessible by integration"),
+        "{}",
+        err
+    );
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn concurrency_follows_remaining_points() {
+    assert_eq!(allowed_concurrency(0), 1);
+    assert_eq!(allowed_concurrency(99), 1);
+    assert_eq!(allowed_concurrency(250), 2);
+    assert_eq!(allowed_concurrency(1_000_000), MAX_CONCURRENCY);
+}
+
+fn rate_limit(
+    cost: usize,
+    remaining: usize,
+    reset_hour: u32,
+) -> RateLimit {
+    RateLimit {
+        cost,
+        remaining,
+        resetAt: Utc.with_ymd_and_hms(2024, 3, 1, reset_hour, 0, 0).unwrap(),
+    }
+}
+
+#[test]
+fn rate_budget_keeps_the_lowest_remaining() {
+    let budget = RateBudget::default();
+    assert!(budget.report().is_none());
+    budget.record(rate_limit(2, 4000, 10));
+    // responses of the same period may come unordered
+    budget.record(rate_limit(1, 3000, 10));
+    budget.record(rate_limit(3, 3500, 10));
+    assert_eq!(budget.last().unwrap().remaining, 3000);
+    // an older period is ignored, a new one replaces the budget
+    budget.record(rate_limit(1, 100, 9));
+    assert_eq!(budget.last().unwrap().remaining, 3000);
+    budget.record(rate_limit(1, 4999, 11));
+    assert_eq!(budget.last().unwrap().remaining, 4999);
+    assert_eq!(budget.spent(), 8);
+    let report = budget.report().unwrap();
+    assert_eq!(report.spent, 8);
+    assert_eq!(report.remaining, 4999);
+}
+
+#[test]
+fn rate_budget_lowers_concurrency() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let budget = RateBudget::default();
+    assert_eq!(budget.concurrency(), MAX_CONCURRENCY);
+    runtime.block_on(async {
+        // plenty of points: permits are given back
+        budget.record(rate_limit(1, 5000, 10));
+        let permit = budget.acquire().await;
+        budget.release(permit);
+        assert_eq!(budget.concurrency(), MAX_CONCURRENCY);
+        // few points: each released permit is forgotten, down to one
+        budget.record(rate_limit(1, 150, 10));
+        for _ in 0..MAX_CONCURRENCY + 5 {
+            let permit = budget.acquire().await;
+            budget.release(permit);
+        }
+        assert_eq!(budget.concurrency(), 1);
+        // the last permit is still there
+        let permit = budget.acquire().await;
+        budget.release(permit);
+    });
+}
+
+#[test]
+fn rate_budget_raises_concurrency_in_a_new_period() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let budget = RateBudget::default();
+    runtime.block_on(async {
+        budget.record(rate_limit(1, 150, 10));
+        for _ in 0..MAX_CONCURRENCY {
+            let permit = budget.acquire().await;
+            budget.release(permit);
+        }
+        assert_eq!(budget.concurrency(), 1);
+        // a late response of the low period changes nothing
+        budget.record(rate_limit(1, 140, 10));
+        assert_eq!(budget.concurrency(), 1);
+        // the budget of the next period allows concurrency again
+        budget.record(rate_limit(1, 5000, 11));
+        assert_eq!(budget.concurrency(), MAX_CONCURRENCY);
+        let mut permits = Vec::new();
+        for _ in 0..MAX_CONCURRENCY {
+            permits.push(budget.acquire().await);
+        }
+        for permit in permits {
+            budget.release(permit);
+        }
+        assert_eq!(budget.concurrency(), MAX_CONCURRENCY);
+    });
+}
+
+#[test]
+fn github_enterprise_without_rate_limit() {
+    // GitHub Enterprise gives no rate limit when rate limiting is disabled
+    let server = MockServer::start(|request| {
+        let query = request.graphql_query();
+        if query.contains("repositoryOwner") {
+            return Response::json(
+                r#"{"data":{"repositoryOwner":{"__typename":"User"},"rateLimit":null}}"#,
+            );
+        }
+        github_alice(&query).unwrap_or_else(|| Response::status(400, "{}"))
+    });
+    let sandbox = Sandbox::new("enterprise");
+    sandbox.set_host("github", &format!("{}/graphql", server.url));
+    sandbox.run_ok(&["track", "alice@mock"]);
+}
+
+/// the query parameter of a path, as a number
+fn query_param(
+    path: &str,
+    name: &str,
+) -> Option<usize> {
+    let (_, query) = path.split_once('?')?;
+    query
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+/// the items of a page, when the server gives at most 2 items
+/// per page whatever the asked page size, over 5 items
+fn short_page(
+    path: &str,
+    item: impl Fn(usize) -> String,
+) -> (usize, String) {
+    let page = query_param(path, "page").unwrap_or(1);
+    let items: Vec<String> = (1..=5).skip((page - 1) * 2).take(2).map(item).collect();
+    (page, format!("[{}]", items.join(",")))
+}
+
+#[test]
+fn gitlab_follows_page_links() {
+    let server = MockServer::start(|request| {
+        let path = request.path.as_str();
+        if path.starts_with("/api/v4/users?username=alice") {
+            return Response::json(r#"[{"name":"Alice"}]"#);
+        }
+        if path.starts_with("/api/v4/users/alice/projects") {
+            assert_eq!(query_param(path, "per_page"), Some(100));
+            let (page, body) = short_page(path, |i| {
+                format!(
+                    r#"{{"id":{0},"path":"p{0}","namespace":{{"full_path":"alice"}},"star_count":{0}}}"#,
+                    i
+                )
+            });
+            let mut response = Response::json(&body);
+            if page < 3 {
+                let next = format!(
+                    "http://{}/api/v4/users/alice/projects?per_page=100&page={}",
+                    request.header("host").unwrap(),
+                    page + 1,
+                );
+                response = response.with_header("Link", &format!(r#"<{}>; rel="next""#, next));
+            }
+            return response;
+        }
+        Response::status(404, "{}")
+    });
+    let sandbox = Sandbox::new("gitlab");
+    sandbox.set_host("gitlab", &format!("{}/api/v4", server.url));
+    sandbox.run_ok(&["follow", "alice@mock"]);
+    sandbox.run_ok(&["gaze"]);
+    let out = sandbox.run_ok(&["list", "--format", "csv", "alice@mock"]);
+    assert_eq!(out.lines().count(), 1 + 5, "{}", out);
+    assert!(out.contains("p5,5"), "{}", out);
+    let auth = server.requests()[0]
+        .header("authorization")
+        .map(String::from);
+    assert_eq!(auth.as_deref(), Some("Bearer secret"));
+}
+
+/// a Gitea server giving short pages, telling the total count
+/// of repositories or not
+fn gitea_server(total_count: bool) -> MockServer {
+    MockServer::start(move |request| {
+        // Gitea logins are case-insensitive, the answers give the canonical case
+        let path = request.path.to_ascii_lowercase();
+        let path = path.as_str();
+        if path == "/api/v1/users/alice" {
+            return Response::json(
+                r#"{"login":"alice","full_name":"Alice","followers_count":3,"following_count":1}"#,
+            );
+        }
+        if path.starts_with("/api/v1/users/alice/repos") {
+            assert_eq!(query_param(path, "limit"), Some(50));
+            let (_, body) = short_page(path, |i| {
+                format!(
+                    r#"{{"id":{0},"name":"r{0}","owner":{{"login":"alice"}},"fork":false,"stars_count":{0}}}"#,
+                    i
+                )
+            });
+            let response = Response::json(&body);
+            return if total_count {
+                response.with_header("X-Total-Count", "5")
+            } else {
+                response
+            };
+        }
+        Response::status(404, "{}")
+    })
+}
+
+#[test]
+fn gitea_pages_to_the_total_count() {
+    let server = gitea_server(true);
+    let sandbox = Sandbox::new("gitea-total");
+    sandbox.set_host("gitea", &format!("{}/api/v1", server.url));
+    sandbox.run_ok(&["follow", "alice@mock"]);
+    sandbox.run_ok(&["gaze"]);
+    let out = sandbox.run_ok(&["list", "--format", "csv", "alice@mock"]);
+    assert_eq!(out.lines().count(), 1 + 5, "{}", out);
+    assert!(out.contains("r5,5"), "{}", out);
+    // the third page is the last one, no need to ask for a fourth
+    assert!(
+        !server.requests().iter().any(|r| r.path.contains("page=4")),
+        "{:?}",
+        server.requests()
+    );
+}
+
+#[test]
+fn gitea_pages_until_empty() {
+    let server = gitea_server(false);
+    let sandbox = Sandbox::new("gitea-empty");
+    sandbox.set_host("gitea", &format!("{}/api/v1", server.url));
+    sandbox.run_ok(&["follow", "alice@mock"]);
+    sandbox.run_ok(&["gaze"]);
+    let out = sandbox.run_ok(&["list", "--format", "csv", "alice@mock"]);
+    assert_eq!(out.lines().count(), 1 + 5, "{}", out);
+}
+
+#[test]
+fn gitea_logins_are_case_insensitive() {
+    let server = gitea_server(true);
+    let sandbox = Sandbox::new("gitea-case");
+    sandbox.set_host("gitea", &format!("{}/api/v1", server.url));
+    sandbox.run_ok(&["follow", "Alice@mock"]);
+    sandbox.run_ok(&["gaze"]);
+    let out = sandbox.run_ok(&["list", "--format", "csv", "Alice@mock"]);
+    assert_eq!(out.lines().count(), 1 + 5, "{}", out);
+    let out = sandbox.run_ok(&["gaze"]);
+    assert!(!out.contains("isappeared"), "{}", out);
+}
@@ -0,0 +1,143 @@
+//! Tests of the notification sinks, with local receivers
+
+mod common;
+
+use {
+    base64::{
+        engine::general_purpose::STANDARD as BASE64,
+        Engine,
+    },
+    common::*,
+    starry::*,
+    std::{
+        thread,
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+};
+
+fn changes() -> Vec<RepoChange> {
+    vec![RepoChange {
+        repo_id: RepoId::new(UserId::parse("alice"), "foo"),
+        kind: ChangeKind::Stars,
+        id: Some("R1".to_string()),
+        old_stars: Some(10),
+        new_stars: Some(30),
+    }]
+}
+
+fn sink(
+    kind: SinkKind,
+    timeout_secs: u64,
+) -> Sink {
+    Sink {
+        kind,
+        threshold: 0.0,
+        timeout: Duration::from_secs(timeout_secs),
+    }
+}
+
+fn send(sink: &Sink) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(sink.send(&changes(), &Conf::default()))
+}
+
+#[test]
+fn mail_message() {
+    let conf = SmtpConf {
+        server: "smtp.example.com".to_string(),
+        port: 587,
+        user: None,
+        password: None,
+        from: "starry@example.com".to_string(),
+        to: vec!["a@example.com".to_string(), "b@example.com".to_string()],
+    };
+    let body = "1 changes\n|alice|**foo**|10|30|\n".repeat(10);
+    let mail = Mail {
+        subject: "starry: 1 changes".to_string(),
+        body: body.clone(),
+    };
+    let message = mail.message(&conf);
+    let (head, encoded) = message.split_once("\r\n\r\n").unwrap();
+    let headers: Vec<&str> = head.split("\r\n").collect();
+    assert_eq!(headers[0], "From: starry@example.com");
+    assert_eq!(headers[1], "To: a@example.com, b@example.com");
+    assert_eq!(headers[2], "Subject: starry: 1 changes");
+    assert!(headers[3].starts_with("Date: "), "{}", head);
+    assert!(headers.contains(&"Content-Transfer-Encoding: base64"));
+    // the body is split in short lines, none of them being a lone dot
+    let lines: Vec<&str> = encoded.split("\r\n").collect();
+    assert!(lines.len() > 1);
+    assert!(lines.iter().all(|line| line.len() <= 76 && *line != "."));
+    let decoded = BASE64.decode(lines.concat()).unwrap();
+    assert_eq!(String::from_utf8(decoded).unwrap(), body);
+}
+
+#[test]
+fn webhook_receives_changes() {
+    let server = MockServer::start(|_| Response::json("{}"));
+    send(&sink(SinkKind::Webhook(server.url.clone()), 5)).unwrap();
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    let body = &requests[0].body;
+    assert!(body.contains(r#""owner":"alice","repo":"foo""#), "{}", body);
+    assert!(
+        body.contains(r#""old_stars":10,"new_stars":30"#),
+        "{}",
+        body
+    );
+}
+
+#[test]
+fn webhook_errors() {
+    let server = MockServer::start(|_| Response::status(500, "{}"));
+    let err = send(&sink(SinkKind::Webhook(server.url.clone()), 5)).unwrap_err();
+    assert!(err.to_string().contains("500"), "{:?}", err);
+}
+
+#[test]
+fn webhook_timeout() {
+    let server = MockServer::start(|_| {
+        thread::sleep(Duration::from_secs(3));
+        Response::json("{}")
+    });
+    let start = Instant::now();
+    let err = send(&sink(SinkKind::Webhook(server.url.clone()), 1)).unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(3));
+    assert!(
+        err.to_string().contains("No completion after 1s"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn command_timeout() {
+    let start = Instant::now();
+    let err = send(&sink(SinkKind::Command("sleep 10".to_string()), 1)).unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(
+        err.to_string().contains("No completion after 1s"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn timeout_configuration() {
+    let mut conf = Conf::default();
+    conf.props
+        .insert(NOTIFY_COMMAND_KEY.to_string(), "cat".to_string());
+    let sinks = Sink::all(&conf).unwrap();
+    assert_eq!(sinks[0].timeout, DEFAULT_NOTIFY_TIMEOUT);
+    conf.props
+        .insert(NOTIFY_TIMEOUT_KEY.to_string(), "5".to_string());
+    let sinks = Sink::all(&conf).unwrap();
+    assert_eq!(sinks[0].timeout, Duration::from_secs(5));
+    conf.props
+        .insert(NOTIFY_TIMEOUT_KEY.to_string(), "soon".to_string());
+    assert!(Sink::all(&conf).is_err());
+}
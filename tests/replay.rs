@@ -0,0 +1,753 @@
+//! End to end tests of the starry binary, with forges replaced
+//! by fixture files so that they run without network.
+
+use std::{
+    fs,
+    io::{
+        BufRead,
+        BufReader,
+    },
+    path::PathBuf,
+    process::{
+        Command,
+        Output,
+        Stdio,
+    },
+    thread,
+    time::Duration,
+};
+
+/// an isolated environment: fixtures, configuration and data
+struct Sandbox {
+    root: PathBuf,
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("starry-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("fixtures/users")).unwrap();
+        fs::create_dir_all(root.join("fixtures/organizations")).unwrap();
+        Self { root }
+    }
+    /// write the repositories an owner has on the fake forge
+    fn set_fixture(
+        &self,
+        kind: &str,
+        owner: &str,
+        csv: &str,
+    ) {
+        let path = self
+            .root
+            .join("fixtures")
+            .join(kind)
+            .join(format!("{}.csv", owner));
+        fs::write(path, csv).unwrap();
+    }
+    /// write the stargazers of a repository, with the dates of their stars
+    fn set_stargazers(
+        &self,
+        owner: &str,
+        repo: &str,
+        csv: &str,
+    ) {
+        let dir = self.root.join("fixtures/stargazers").join(owner);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("{}.csv", repo)), csv).unwrap();
+    }
+    /// write the repositories a user starred, with the dates of their stars
+    fn set_starred(
+        &self,
+        user: &str,
+        csv: &str,
+    ) {
+        let dir = self.root.join("fixtures/starred");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("{}.csv", user)), csv).unwrap();
+    }
+    /// write the numbers of followers and followed users of a user
+    fn set_follows(
+        &self,
+        user: &str,
+        followers: usize,
+        following: usize,
+    ) {
+        let dir = self.root.join("fixtures/follows");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(format!("{}.csv", user)),
+            format!("followers,following\n{},{}\n", followers, following),
+        )
+        .unwrap();
+    }
+    /// the starry binary, reading the fixtures of the sandbox
+    fn starry(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_starry"));
+        command.env("STARRY_FIXTURES", self.root.join("fixtures"));
+        command
+    }
+    /// a starry command on this sandbox, to be run
+    fn command(
+        &self,
+        args: &[&str],
+    ) -> Command {
+        let mut command = self.starry();
+        command
+            .arg("--config")
+            .arg(self.root.join("config.json"))
+            .arg("--data-dir")
+            .arg(self.root.join("data"))
+            .args(args);
+        command
+    }
+    /// a command whose configuration and database are in the given
+    /// directory of the sandbox, set as `STARRY_HOME`
+    fn home_command(
+        &self,
+        home: &str,
+        args: &[&str],
+    ) -> Command {
+        let mut command = self.starry();
+        command.args(args).env("STARRY_HOME", self.root.join(home));
+        command
+    }
+    fn run(
+        &self,
+        args: &[&str],
+    ) -> Output {
+        Self::check(self.command(args), args)
+    }
+    /// run the command, checking it succeeded
+    fn check(
+        mut command: Command,
+        args: &[&str],
+    ) -> Output {
+        let output = command.output().unwrap();
+        assert!(
+            output.status.success(),
+            "starry {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr),
+        );
+        output
+    }
+    fn stdout(
+        &self,
+        args: &[&str],
+    ) -> String {
+        String::from_utf8(self.run(args).stdout).unwrap()
+    }
+    /// run with `STARRY_HOME` set to the given directory of the sandbox
+    fn home_stdout(
+        &self,
+        home: &str,
+        args: &[&str],
+    ) -> String {
+        let output = Self::check(self.home_command(home, args), args);
+        String::from_utf8(output.stdout).unwrap()
+    }
+    /// fetch and compare, making sure the observation isn't
+    /// stored with the same time than the previous one (times
+    /// are precise to the second)
+    fn gaze(&self) -> String {
+        self.gaze_with(&[])
+    }
+    fn gaze_with(
+        &self,
+        options: &[&str],
+    ) -> String {
+        thread::sleep(Duration::from_millis(1100));
+        let mut args = vec!["gaze"];
+        args.extend_from_slice(options);
+        self.stdout(&args)
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+#[test]
+fn check_and_follow() {
+    let sandbox = Sandbox::new("check");
+    sandbox.set_fixture(
+        "users",
+        "alice",
+        "repo_name,id,stars\nfoo,R1,10\nbar,R2,3\n",
+    );
+    sandbox.set_fixture(
+        "organizations",
+        "acme",
+        "repo_name,id,stars\nrocket,R3,100\n",
+    );
+    let out = sandbox.stdout(&["check", "alice"]);
+    assert!(
+        out.contains("User alice has 2 non forked repositories"),
+        "{}",
+        out
+    );
+    let out = sandbox.stdout(&["check", "acme"]);
+    assert!(
+        out.contains("Organization acme has 1 non forked repositories"),
+        "{}",
+        out
+    );
+    let out = sandbox.stdout(&["check", "acme/rocket"]);
+    assert!(
+        out.contains("Repository acme/rocket has 100 stars"),
+        "{}",
+        out
+    );
+    sandbox.stdout(&["follow", "alice"]);
+    sandbox.stdout(&["follow", "acme"]);
+    let conf = fs::read_to_string(sandbox.root.join("config.json")).unwrap();
+    assert!(conf.contains("\"alice\""), "{}", conf);
+    assert!(conf.contains("\"acme\""), "{}", conf);
+}
+
+#[test]
+fn gaze_reports_changes() {
+    let sandbox = Sandbox::new("gaze");
+    sandbox.set_fixture(
+        "users",
+        "alice",
+        "repo_name,id,stars\nfoo,R1,10\nbar,R2,3\n",
+    );
+    sandbox.stdout(&["follow", "alice"]);
+    // the first observation is the reference
+    let out = sandbox.gaze();
+    assert!(out.contains("no change"), "{}", out);
+    let out = sandbox.gaze();
+    assert!(out.contains("no change"), "{}", out);
+    // foo gets stars, bar is renamed, baz appears
+    sandbox.set_fixture(
+        "users",
+        "alice",
+        "repo_name,id,stars\nfoo,R1,25\nbaz,R2,3\nnew,R4,1\n",
+    );
+    let out = sandbox.gaze();
+    assert!(out.contains("3 changes"), "{}", out);
+    assert!(out.contains("foo"), "{}", out);
+    assert!(out.contains("renamed from bar"), "{}", out);
+    assert!(out.contains("new"), "{}", out);
+    // foo disappears
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nbaz,R2,3\nnew,R4,1\n");
+    let out = sandbox.gaze();
+    assert!(out.contains("disappeared"), "{}", out);
+    // offline, the last stored changes are shown again, the forge isn't read
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nbaz,R2,50\n");
+    let out = sandbox.stdout(&["gaze", "--offline"]);
+    assert!(out.contains("disappeared"), "{}", out);
+    assert!(!out.contains("50"), "{}", out);
+    let out = sandbox.stdout(&["extract", "alice/foo", "alice"]);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[0], "time,alice/foo,alice");
+    assert!(lines[1].ends_with(",10,13"), "{}", out);
+    assert!(lines[2].ends_with(",25,29"), "{}", out);
+    assert!(lines[3].ends_with(",,4"), "{}", out);
+}
+
+#[test]
+fn unfollowed_repositories_are_not_reported() {
+    let sandbox = Sandbox::new("unfollow");
+    sandbox.set_fixture(
+        "users",
+        "alice",
+        "repo_name,id,stars\nfoo,R1,10\nbar,R2,3\nbaz,R3,1\n",
+    );
+    sandbox.stdout(&["follow", "alice"]);
+    sandbox.stdout(&["follow", "alice/foo"]);
+    sandbox.stdout(&["follow", "alice/bar"]);
+    sandbox.gaze();
+    // bar and baz leave the followed repositories while still existing
+    sandbox.stdout(&["unfollow", "alice"]);
+    sandbox.stdout(&["unfollow", "alice/bar"]);
+    let out = sandbox.gaze();
+    assert!(out.contains("no change"), "{}", out);
+    assert!(!out.contains("disappeared"), "{}", out);
+    // while a repository which doesn't exist anymore has disappeared
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nbar,R2,3\n");
+    let out = sandbox.gaze();
+    assert!(out.contains("disappeared"), "{}", out);
+    assert!(out.contains("foo"), "{}", out);
+}
+
+#[test]
+fn long_durations_are_rejected() {
+    let sandbox = Sandbox::new("durations");
+    let fails = |args: &[&str], message: &str| {
+        let output = sandbox.command(args).output().unwrap();
+        let err = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "starry {:?} succeeded", args);
+        assert!(!err.contains("panicked"), "{}", err);
+        assert!(err.contains(message), "{}", err);
+    };
+    fails(&["gaps", "--min", "99999999999999d"], "Duration too long");
+    fails(
+        &["report", "--since", "9999999999999999w"],
+        "Duration too long",
+    );
+    fails(
+        &["report", "--since", "100000000d"],
+        "--since is too far in the past",
+    );
+}
+
+#[test]
+fn starry_home_separates_databases() {
+    let sandbox = Sandbox::new("home");
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,10\n");
+    sandbox.set_fixture("users", "bob", "repo_name,id,stars\nbar,R2,20\n");
+    sandbox.home_stdout("project-a", &["follow", "alice"]);
+    sandbox.home_stdout("project-a", &["gaze"]);
+    sandbox.home_stdout("project-b", &["follow", "bob"]);
+    sandbox.home_stdout("project-b", &["gaze"]);
+    assert!(sandbox.root.join("project-a/config.json").exists());
+    assert!(sandbox.root.join("project-a/stars/alice").exists());
+    assert!(!sandbox.root.join("project-a/stars/bob").exists());
+    assert_eq!(
+        sandbox.home_stdout("project-a", &["list"]),
+        "name,stars\nalice,10\n"
+    );
+    assert_eq!(
+        sandbox.home_stdout("project-b", &["list"]),
+        "name,stars\nbob,20\n"
+    );
+}
+
+#[test]
+fn watch_prints_changes() {
+    let sandbox = Sandbox::new("watch");
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,10\n");
+    sandbox.stdout(&["follow", "alice"]);
+    sandbox.gaze();
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,12\n");
+    thread::sleep(Duration::from_millis(1100));
+    let mut child = sandbox
+        .command(&["watch", "--interval", "1h"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(
+        line.ends_with("alice/foo rised from 10 to 12 - https://github.com/alice/foo\n"),
+        "{}",
+        line
+    );
+}
+
+#[test]
+fn command_notifications() {
+    let sandbox = Sandbox::new("notify");
+    sandbox.set_fixture(
+        "users",
+        "alice",
+        "repo_name,id,stars\nfoo,R1,10\nbar,R2,3\n",
+    );
+    let output = sandbox.root.join("notification.json");
+    let command = format!("cat > {}", output.display());
+    sandbox.stdout(&["follow", "alice"]);
+    sandbox.stdout(&["set", "notify-command", &command]);
+    sandbox.stdout(&["set", "notify-command-threshold", "5"]);
+    sandbox.gaze();
+    // foo gets many stars, bar only one: only foo is interesting enough
+    sandbox.set_fixture(
+        "users",
+        "alice",
+        "repo_name,id,stars\nfoo,R1,30\nbar,R2,4\n",
+    );
+    sandbox.gaze();
+    let json = fs::read_to_string(&output).unwrap();
+    assert!(json.contains(r#""owner":"alice","repo":"foo""#), "{}", json);
+    assert!(
+        json.contains(r#""old_stars":10,"new_stars":30"#),
+        "{}",
+        json
+    );
+    assert!(!json.contains(r#""repo":"bar""#), "{}", json);
+}
+
+#[test]
+fn output_formats() {
+    let sandbox = Sandbox::new("formats");
+    sandbox.set_fixture(
+        "users",
+        "alice",
+        "repo_name,id,stars\nfoo,R1,10\nbar,R2,3\n",
+    );
+    sandbox.stdout(&["follow", "alice"]);
+    sandbox.gaze();
+    sandbox.set_fixture(
+        "users",
+        "alice",
+        "repo_name,id,stars\nfoo,R1,30\nbar,R2,3\n",
+    );
+    let out = sandbox.gaze_with(&["--format", "ndjson"]);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", out);
+    assert!(
+        lines[0].starts_with(
+            r#"{"owner":"alice","repo":"foo","event":null,"old_stars":10,"new_stars":30,"value":"#
+        ),
+        "{}",
+        out
+    );
+    let out = sandbox.stdout(&["extract", "--format", "json", "alice"]);
+    assert!(out.contains(r#""alice": 13"#), "{}", out);
+    assert!(out.contains(r#""alice": 33"#), "{}", out);
+    let out = sandbox.stdout(&["list", "--format", "markdown", "alice"]);
+    assert!(out.starts_with("|name|stars|\n|-|-|\n"), "{}", out);
+    assert!(out.contains("|foo|30|"), "{}", out);
+    let out = sandbox.stdout(&["list", "--format", "csv"]);
+    assert_eq!(out, "name,stars\nalice,33\n");
+}
+
+#[test]
+fn output_sections() {
+    let sandbox = Sandbox::new("sections");
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,10\n");
+    sandbox.set_follows("alice", 10, 3);
+    sandbox.set_starred(
+        "alice",
+        "starred_at,repo,stars\n2024-03-01T10:00:00Z,bob/old,50\n",
+    );
+    sandbox.stdout(&["track", "alice"]);
+    sandbox.gaze();
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,30\n");
+    sandbox.set_follows("alice", 12, 3);
+    let yesterday = chrono::Utc::now() - chrono::Duration::days(1);
+    sandbox.set_starred(
+        "alice",
+        &format!(
+            "starred_at,repo,stars\n2024-03-01T10:00:00Z,bob/old,50\n{},carol/new,7\n",
+            yesterday.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        ),
+    );
+    // in JSON, the follow changes and the starred repositories are
+    // written with the changes of the repositories
+    thread::sleep(Duration::from_millis(1100));
+    let no_save = |options: &[&str]| {
+        let mut args = vec!["--no-save", "gaze"];
+        args.extend_from_slice(options);
+        sandbox.stdout(&args)
+    };
+    let out = no_save(&["--format", "json"]);
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    let keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+    assert_eq!(keys, ["changes", "follow_changes", "starred"], "{}", out);
+    assert_eq!(json["changes"][0]["repo"], "foo", "{}", out);
+    assert_eq!(json["changes"][0]["new_stars"], 30, "{}", out);
+    let follow_change = &json["follow_changes"][0];
+    assert_eq!(follow_change["user"], "alice", "{}", out);
+    assert_eq!(follow_change["old_followers"], 10, "{}", out);
+    assert_eq!(follow_change["new_followers"], 12, "{}", out);
+    assert_eq!(follow_change["new_following"], 3, "{}", out);
+    assert_eq!(json["starred"].as_array().map(Vec::len), Some(1), "{}", out);
+    let starred = &json["starred"][0];
+    assert_eq!(starred["user"], "alice", "{}", out);
+    assert_eq!(starred["repo"], "carol/new", "{}", out);
+    assert_eq!(starred["stars"], 7, "{}", out);
+    // a single list is chosen with --feed, the changes by default
+    let out = no_save(&["--format", "json", "--feed", "changes"]);
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(json[0]["repo"], "foo", "{}", out);
+    let out = no_save(&["--format", "csv"]);
+    assert!(
+        out.starts_with("owner,repo,event,old_stars,new_stars,"),
+        "{}",
+        out
+    );
+    assert_eq!(out.lines().count(), 2, "{}", out);
+    let out = no_save(&["--format", "csv", "--feed", "follows"]);
+    assert_eq!(
+        out,
+        "user,old_followers,new_followers,old_following,new_following\nalice,10,12,3,3\n"
+    );
+    let out = no_save(&["--format", "ndjson", "--feed", "starred"]);
+    assert_eq!(out.lines().count(), 1, "{}", out);
+    assert!(
+        out.starts_with(r#"{"user":"alice","repo":"carol/new","#),
+        "{}",
+        out
+    );
+    let output = sandbox
+        .command(&["gaze", "--feed", "starred"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn backfill_from_star_dates() {
+    let sandbox = Sandbox::new("backfill");
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,3\nbar,R2,1\n");
+    sandbox.set_stargazers(
+        "alice",
+        "foo",
+        "login,starred_at\nbob,2024-03-01T10:00:00Z\ncarol,2024-03-01T18:00:00Z\ndan,2024-03-05T08:00:00Z\n",
+    );
+    sandbox.set_stargazers(
+        "alice",
+        "bar",
+        "login,starred_at\nbob,2024-03-04T10:00:00Z\n",
+    );
+    sandbox.stdout(&["follow", "alice"]);
+    let out = sandbox.stdout(&["backfill", "alice"]);
+    assert!(
+        out.contains("3 reconstructed observations of alice written"),
+        "{}",
+        out
+    );
+    let out = sandbox.stdout(&["extract", "alice", "alice/foo", "alice/bar"]);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "time,alice,alice/foo,alice/bar,reconstructed",
+            "2024-03-02T00:00:00Z,2,2,,true",
+            "2024-03-05T00:00:00Z,3,2,1,true",
+            "2024-03-06T00:00:00Z,4,3,1,true",
+        ],
+    );
+    let file = sandbox
+        .root
+        .join("data/stars/alice/2024-03-02T00:00:00Z.csv");
+    let csv = fs::read_to_string(file).unwrap();
+    assert!(
+        csv.starts_with("repo_name,id,stars,reconstructed\n"),
+        "{}",
+        csv
+    );
+    // nothing is reconstructed over already stored observations
+    sandbox.gaze();
+    let out = sandbox.stdout(&["backfill", "alice"]);
+    assert!(out.contains("0 reconstructed observations"), "{}", out);
+    // the real observations aren't flagged
+    let out = sandbox.stdout(&["extract", "alice"]);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 5, "{}", out);
+    assert!(lines[3].ends_with(",4,true"), "{}", out);
+    assert!(lines[4].ends_with(",4,false"), "{}", out);
+    let out = sandbox.stdout(&["graph", "alice"]);
+    assert!(out.contains('┄'), "{}", out);
+    assert!(
+        out.contains("reconstructed from the dates of the stars"),
+        "{}",
+        out
+    );
+    // a report over reconstructed observations tells it
+    let out = sandbox.stdout(&[
+        "report",
+        "--from",
+        "2024-03-01T12:00:00Z",
+        "--to",
+        "2024-03-05T12:00:00Z",
+    ]);
+    assert!(
+        out.contains("Note: the observations of alice at"),
+        "{}",
+        out
+    );
+    assert!(out.contains(" and "), "{}", out);
+    // here only the start of the period is reconstructed
+    let out = sandbox.stdout(&["report", "--since", "1d"]);
+    assert!(
+        out.contains("Note: the observations of alice at"),
+        "{}",
+        out
+    );
+    assert!(!out.contains(" and "), "{}", out);
+}
+
+#[test]
+fn backfill_writes_nothing_when_a_history_fails() {
+    let sandbox = Sandbox::new("backfill-failed");
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,2\nbar,R2,1\n");
+    sandbox.set_stargazers(
+        "alice",
+        "foo",
+        "login,starred_at\nbob,2024-03-01T10:00:00Z\ncarol,2024-03-03T10:00:00Z\n",
+    );
+    // there's no stargazers fixture for bar, so its history can't be read
+    sandbox.stdout(&["follow", "alice"]);
+    let output = sandbox.command(&["backfill", "alice"]).output().unwrap();
+    assert!(!output.status.success());
+    let err = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        err.contains("The stars of 1 repositories couldn't be read"),
+        "{}",
+        err
+    );
+    let out = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        out.contains("0 reconstructed observations of alice written"),
+        "{}",
+        out
+    );
+    assert!(!sandbox.root.join("data/stars/alice").exists());
+    // so that a later backfill, once the history is readable, is complete
+    sandbox.set_stargazers(
+        "alice",
+        "bar",
+        "login,starred_at\nbob,2024-03-02T10:00:00Z\n",
+    );
+    let out = sandbox.stdout(&["backfill", "alice"]);
+    assert!(
+        out.contains("3 reconstructed observations of alice written"),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn track_stargazers() {
+    let sandbox = Sandbox::new("who");
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,2\n");
+    sandbox.set_stargazers(
+        "alice",
+        "foo",
+        "login,starred_at\nbob,2024-03-01T10:00:00Z\ncarol,2024-03-02T10:00:00Z\n",
+    );
+    sandbox.stdout(&["track", "alice/foo"]);
+    let conf = fs::read_to_string(sandbox.root.join("config.json")).unwrap();
+    assert!(conf.contains("\"alice/foo\""), "{}", conf);
+    sandbox.gaze();
+    // carol leaves, dan comes
+    let yesterday = chrono::Utc::now() - chrono::Duration::days(1);
+    sandbox.set_stargazers(
+        "alice",
+        "foo",
+        &format!(
+            "login,starred_at\nbob,2024-03-01T10:00:00Z\ndan,{}\n",
+            yesterday.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        ),
+    );
+    sandbox.gaze();
+    let out = sandbox.stdout(&["who", "alice/foo", "--since", "7d"]);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", out);
+    assert_eq!(lines[0], "time,login,event");
+    assert!(lines[1].ends_with(",dan,star"), "{}", out);
+    assert!(lines[2].ends_with(",carol,unstar"), "{}", out);
+    // with a longer period, the first stars are there too
+    let out = sandbox.stdout(&["who", "alice/foo", "--since", "10000d"]);
+    assert!(out.contains(",bob,star"), "{}", out);
+}
+
+#[test]
+fn starred_feed() {
+    let sandbox = Sandbox::new("starred");
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,2\n");
+    sandbox.set_starred(
+        "alice",
+        "starred_at,repo,stars\n2024-03-01T10:00:00Z,bob/old,50\n",
+    );
+    sandbox.stdout(&["track", "alice"]);
+    let conf = fs::read_to_string(sandbox.root.join("config.json")).unwrap();
+    assert!(conf.contains("\"starred_feeds\""), "{}", conf);
+    // the first fetch is only a reference
+    let out = sandbox.gaze();
+    assert!(!out.contains("bob/old"), "{}", out);
+    let yesterday = chrono::Utc::now() - chrono::Duration::days(1);
+    sandbox.set_starred(
+        "alice",
+        &format!(
+            "starred_at,repo,stars\n2024-03-01T10:00:00Z,bob/old,50\n{},carol/new,7\n",
+            yesterday.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        ),
+    );
+    let out = sandbox.gaze();
+    assert!(
+        out.contains("1 repositories starred by the people you follow"),
+        "{}",
+        out
+    );
+    assert!(out.contains("carol/new"), "{}", out);
+    assert!(!out.contains("bob/old"), "{}", out);
+    // already seen stars aren't reported again
+    let out = sandbox.gaze();
+    assert!(!out.contains("carol/new"), "{}", out);
+    // but they're in the reports of their period
+    let out = sandbox.stdout(&["report", "--since", "7d"]);
+    assert!(out.contains("carol/new"), "{}", out);
+    assert!(!out.contains("bob/old"), "{}", out);
+}
+
+#[test]
+fn follower_counts() {
+    let sandbox = Sandbox::new("follows");
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,2\n");
+    sandbox.set_follows("alice", 10, 3);
+    let out = sandbox.stdout(&["check", "alice"]);
+    assert!(
+        out.contains("alice has 10 followers and follows 3 users"),
+        "{}",
+        out
+    );
+    sandbox.stdout(&["follow", "alice"]);
+    let out = sandbox.gaze();
+    assert!(!out.contains("changes in followers"), "{}", out);
+    sandbox.set_follows("alice", 12, 3);
+    let out = sandbox.gaze();
+    assert!(out.contains("1 changes in followers"), "{}", out);
+    assert!(out.contains("+2"), "{}", out);
+    // unchanged counts aren't stored again
+    sandbox.gaze();
+    let out = sandbox.stdout(&["extract", "alice:followers", "alice:following"]);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", out);
+    assert_eq!(lines[0], "time,alice:followers,alice:following");
+    assert!(lines[1].ends_with(",10,3"), "{}", out);
+    assert!(lines[2].ends_with(",12,3"), "{}", out);
+    let out = sandbox.stdout(&["report", "--since", "7d"]);
+    assert!(out.contains("+2"), "{}", out);
+    // the changes of stars are reported even when the followers can't be read
+    fs::write(
+        sandbox.root.join("fixtures/follows/alice.csv"),
+        "followers\nmany\n",
+    )
+    .unwrap();
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,7\n");
+    let output = sandbox.run(&["gaze"]);
+    let out = String::from_utf8_lossy(&output.stdout);
+    assert!(out.contains("1 changes"), "{}", out);
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Error reading the followers"),
+        "{:?}",
+        output
+    );
+}
+
+#[test]
+fn graph_in_terminal() {
+    let sandbox = Sandbox::new("graph");
+    sandbox.set_fixture("users", "alice", "repo_name,id,stars\nfoo,R1,2\nbar,R2,5\n");
+    sandbox.stdout(&["follow", "alice"]);
+    sandbox.gaze();
+    sandbox.set_fixture(
+        "users",
+        "alice",
+        "repo_name,id,stars\nfoo,R1,12\nbar,R2,6\n",
+    );
+    sandbox.gaze();
+    let out = sandbox.stdout(&["graph", "alice/foo", "--height", "6"]);
+    let lines: Vec<&str> = out.lines().collect();
+    // the plot, the x axis and its labels, the legend
+    assert_eq!(lines.len(), 6 + 2 + 1, "{}", out);
+    assert!(lines[0].starts_with("12┤"), "{}", out);
+    assert!(lines[5].starts_with(" 2┤"), "{}", out);
+    assert!(out.contains('⣀') || out.contains('⠉'), "{}", out);
+    assert!(lines[8].ends_with("alice/foo"), "{}", out);
+    // without color, several series are told apart by their markers
+    let out = sandbox.stdout(&["graph", "alice/foo", "alice/bar"]);
+    assert!(out.contains("• alice/foo"), "{}", out);
+    assert!(out.contains("+ alice/bar"), "{}", out);
+}